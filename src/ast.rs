@@ -0,0 +1,504 @@
+//! The decompiled code as a tree, built from the Sierra statements of a function and
+//! turned into text by the printer. Types are kept as the names they are printed with.
+
+use cairo_lang_sierra::ids::VarId;
+
+/// A variable of the decompiled code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Var {
+    /// A Sierra variable, `v{id}`.
+    Sierra(u64),
+    /// Whether the operation defining the Sierra variable overflowed, `v{id}_overflowed`.
+    Overflowed(u64),
+    /// The value a pattern binds in place of a hoisted Sierra variable, `value_{id}`.
+    Value(u64),
+}
+
+impl From<&VarId> for Var {
+    fn from(var: &VarId) -> Self {
+        Var::Sierra(var.id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitXor,
+    BitOr,
+    Eq,
+}
+
+impl BinaryOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitXor => "^",
+            BinaryOp::BitOr => "|",
+            BinaryOp::Eq => "==",
+        }
+    }
+
+    /// How tightly the operator binds, higher binding tighter.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 5,
+            BinaryOp::Add | BinaryOp::Sub => 4,
+            BinaryOp::BitAnd => 3,
+            BinaryOp::BitXor => 2,
+            BinaryOp::BitOr => 1,
+            BinaryOp::Eq => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Var(Var),
+    /// A number or short string, as printed.
+    Literal(String),
+    /// A call to a function, libfunc or enum variant by its path.
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    MethodCall {
+        receiver: Box<Expr>,
+        method: String,
+        args: Vec<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Not(Box<Expr>),
+    /// Propagates the panic of a result, `expr?`.
+    Try(Box<Expr>),
+    /// A snapshot of a value, `@expr`.
+    Snapshot(Box<Expr>),
+    Tuple(Vec<Expr>),
+    /// A struct built from its members in order, with their names if they are known or
+    /// `field_{i}` otherwise, as Sierra doesn't keep them.
+    Struct(Vec<(String, Expr)>),
+}
+
+impl Expr {
+    pub fn literal(value: impl ToString) -> Self {
+        Expr::Literal(value.to_string())
+    }
+
+    pub fn call(name: impl Into<String>, args: Vec<Expr>) -> Self {
+        Expr::Call {
+            name: name.into(),
+            args,
+        }
+    }
+
+    pub fn method(receiver: Expr, method: impl Into<String>, args: Vec<Expr>) -> Self {
+        Expr::MethodCall {
+            receiver: Box::new(receiver),
+            method: method.into(),
+            args,
+        }
+    }
+
+    pub fn binary(lhs: Expr, op: BinaryOp, rhs: Expr) -> Self {
+        Expr::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        }
+    }
+
+    /// The variables the expression reads, once per read.
+    pub fn vars(&self) -> Vec<Var> {
+        match self {
+            Expr::Var(var) => vec![*var],
+            Expr::Literal(_) => vec![],
+            Expr::Call { args, .. } | Expr::Tuple(args) => {
+                args.iter().flat_map(Expr::vars).collect()
+            }
+            Expr::Struct(members) => members.iter().flat_map(|(_, x)| x.vars()).collect(),
+            Expr::MethodCall { receiver, args, .. } => std::iter::once(&**receiver)
+                .chain(args)
+                .flat_map(Expr::vars)
+                .collect(),
+            Expr::Binary { lhs, rhs, .. } => [lhs, rhs].iter().flat_map(|x| x.vars()).collect(),
+            Expr::Not(value) | Expr::Try(value) | Expr::Snapshot(value) => value.vars(),
+        }
+    }
+
+    /// Calls `f` on every variable the expression reads.
+    pub fn visit_vars_mut(&mut self, f: &mut impl FnMut(&mut Var)) {
+        match self {
+            Expr::Var(var) => f(var),
+            Expr::Literal(_) => {}
+            Expr::Call { args, .. } | Expr::Tuple(args) => {
+                for arg in args {
+                    arg.visit_vars_mut(f);
+                }
+            }
+            Expr::Struct(members) => {
+                for (_, member) in members {
+                    member.visit_vars_mut(f);
+                }
+            }
+            Expr::MethodCall { receiver, args, .. } => {
+                receiver.visit_vars_mut(f);
+                for arg in args {
+                    arg.visit_vars_mut(f);
+                }
+            }
+            Expr::Binary { lhs, rhs, .. } => {
+                lhs.visit_vars_mut(f);
+                rhs.visit_vars_mut(f);
+            }
+            Expr::Not(value) | Expr::Try(value) | Expr::Snapshot(value) => value.visit_vars_mut(f),
+        }
+    }
+}
+
+impl From<Var> for Expr {
+    fn from(var: Var) -> Self {
+        Expr::Var(var)
+    }
+}
+
+impl From<&VarId> for Expr {
+    fn from(var: &VarId) -> Self {
+        Expr::Var(var.into())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    Var(Var),
+    /// A variable along with its type, `v0: u32`.
+    Typed(Var, String),
+    Tuple(Vec<Pattern>),
+    /// A struct by the names of its members, see [`Expr::Struct`].
+    Struct(Vec<(String, Pattern)>),
+    /// The members of a snapshot, which are snapshots themselves, `@pattern`.
+    Snapshot(Box<Pattern>),
+    /// Matches a value without binding it, `_`.
+    Wildcard,
+    /// An enum variant by its path, with the pattern of its value if it is bound.
+    Variant {
+        path: String,
+        value: Option<Box<Pattern>>,
+    },
+}
+
+impl Pattern {
+    /// The variables bound by the pattern.
+    pub fn vars(&self) -> Vec<Var> {
+        match self {
+            Pattern::Var(var) | Pattern::Typed(var, _) => vec![*var],
+            Pattern::Tuple(patterns) => patterns.iter().flat_map(Pattern::vars).collect(),
+            Pattern::Struct(members) => members.iter().flat_map(|(_, x)| x.vars()).collect(),
+            Pattern::Snapshot(pattern) => pattern.vars(),
+            Pattern::Variant { value, .. } => value.iter().flat_map(|x| x.vars()).collect(),
+            Pattern::Wildcard => vec![],
+        }
+    }
+
+    /// The pattern with the variables for which `unused` holds replaced by wildcards, and the
+    /// tuples and structs left with wildcards only replaced by one.
+    pub fn without(self, unused: &impl Fn(Var) -> bool) -> Self {
+        match self {
+            Pattern::Var(var) | Pattern::Typed(var, _) if unused(var) => Pattern::Wildcard,
+            Pattern::Tuple(patterns) => {
+                let patterns: Vec<Pattern> =
+                    patterns.into_iter().map(|x| x.without(unused)).collect();
+                match !patterns.is_empty() && patterns.iter().all(|x| *x == Pattern::Wildcard) {
+                    true => Pattern::Wildcard,
+                    false => Pattern::Tuple(patterns),
+                }
+            }
+            Pattern::Struct(members) => {
+                let members: Vec<(String, Pattern)> = members
+                    .into_iter()
+                    .map(|(name, x)| (name, x.without(unused)))
+                    .collect();
+                match !members.is_empty() && members.iter().all(|(_, x)| *x == Pattern::Wildcard) {
+                    true => Pattern::Wildcard,
+                    false => Pattern::Struct(members),
+                }
+            }
+            Pattern::Snapshot(pattern) => match pattern.without(unused) {
+                Pattern::Wildcard => Pattern::Wildcard,
+                pattern => Pattern::Snapshot(Box::new(pattern)),
+            },
+            Pattern::Variant { path, value } => Pattern::Variant {
+                path,
+                value: value.map(|x| Box::new(x.without(unused))),
+            },
+            pattern => pattern,
+        }
+    }
+
+    /// Calls `f` on every variable the pattern binds.
+    pub fn visit_vars_mut(&mut self, f: &mut impl FnMut(&mut Var)) {
+        match self {
+            Pattern::Var(var) | Pattern::Typed(var, _) => f(var),
+            Pattern::Tuple(patterns) => {
+                for pattern in patterns {
+                    pattern.visit_vars_mut(f);
+                }
+            }
+            Pattern::Struct(members) => {
+                for (_, member) in members {
+                    member.visit_vars_mut(f);
+                }
+            }
+            Pattern::Snapshot(pattern) => pattern.visit_vars_mut(f),
+            Pattern::Variant { value, .. } => {
+                if let Some(value) = value {
+                    value.visit_vars_mut(f);
+                }
+            }
+            Pattern::Wildcard => {}
+        }
+    }
+}
+
+impl From<Var> for Pattern {
+    fn from(var: Var) -> Self {
+        Pattern::Var(var)
+    }
+}
+
+impl From<&VarId> for Pattern {
+    fn from(var: &VarId) -> Self {
+        Pattern::Var(var.into())
+    }
+}
+
+pub type Block = Vec<Stmt>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stmt {
+    /// Declares a variable which is assigned later on, `let mut v0: u32;`.
+    Declare {
+        mutable: bool,
+        var: Var,
+        ty: String,
+    },
+    /// Binds a new variable, `let v0: u32 = expr;`. The comment follows on the same line.
+    Let {
+        mutable: bool,
+        pattern: Pattern,
+        ty: Option<String>,
+        expr: Expr,
+        comment: Option<String>,
+    },
+    /// Assigns a declared variable, `v0 = expr;`.
+    Assign {
+        pattern: Pattern,
+        expr: Expr,
+        comment: Option<String>,
+    },
+    /// An expression whose value isn't bound, `expr;`.
+    Expr {
+        expr: Expr,
+        comment: Option<String>,
+    },
+    If {
+        cond: Expr,
+        then: Block,
+        otherwise: Block,
+    },
+    IfLet {
+        pattern: Pattern,
+        expr: Expr,
+        then: Block,
+        otherwise: Block,
+    },
+    Match {
+        expr: Expr,
+        arms: Vec<Arm>,
+    },
+    Loop(Block),
+    Continue,
+    Break,
+    Return(Vec<Expr>),
+    /// Panics with the data, `panic!(data);`. The comment follows on the same line.
+    Panic {
+        data: Expr,
+        comment: Option<String>,
+    },
+    /// A line comment, without the leading `//`.
+    Comment(String),
+}
+
+impl Stmt {
+    /// Binds `expr` to `pattern`, with a plain assignment if the variables are declared.
+    pub fn bind(declared: bool, pattern: Pattern, ty: Option<String>, expr: Expr) -> Self {
+        if declared {
+            Stmt::Assign {
+                pattern,
+                expr,
+                comment: None,
+            }
+        } else {
+            Stmt::Let {
+                mutable: false,
+                pattern,
+                ty,
+                expr,
+                comment: None,
+            }
+        }
+    }
+
+    pub fn expr(expr: Expr) -> Self {
+        Stmt::Expr {
+            expr,
+            comment: None,
+        }
+    }
+
+    /// The variables the statement binds, declares or assigns, including the ones bound by
+    /// the patterns of its arms but not by the statements of its blocks.
+    pub fn defined_vars(&self) -> Vec<Var> {
+        match self {
+            Stmt::Declare { var, .. } => vec![*var],
+            Stmt::Let { pattern, .. }
+            | Stmt::Assign { pattern, .. }
+            | Stmt::IfLet { pattern, .. } => pattern.vars(),
+            Stmt::Match { arms, .. } => arms.iter().flat_map(|x| x.pattern.vars()).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// The expressions the statement evaluates, not counting the ones of its blocks.
+    pub fn exprs(&self) -> Vec<&Expr> {
+        match self {
+            Stmt::Let { expr, .. }
+            | Stmt::Assign { expr, .. }
+            | Stmt::Expr { expr, .. }
+            | Stmt::If { cond: expr, .. }
+            | Stmt::IfLet { expr, .. }
+            | Stmt::Match { expr, .. }
+            | Stmt::Panic { data: expr, .. } => vec![expr],
+            Stmt::Return(values) => values.iter().collect(),
+            Stmt::Declare { .. }
+            | Stmt::Loop(_)
+            | Stmt::Continue
+            | Stmt::Break
+            | Stmt::Comment(_) => vec![],
+        }
+    }
+
+    pub fn exprs_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Stmt::Let { expr, .. }
+            | Stmt::Assign { expr, .. }
+            | Stmt::Expr { expr, .. }
+            | Stmt::If { cond: expr, .. }
+            | Stmt::IfLet { expr, .. }
+            | Stmt::Match { expr, .. }
+            | Stmt::Panic { data: expr, .. } => vec![expr],
+            Stmt::Return(values) => values.iter_mut().collect(),
+            Stmt::Declare { .. }
+            | Stmt::Loop(_)
+            | Stmt::Continue
+            | Stmt::Break
+            | Stmt::Comment(_) => vec![],
+        }
+    }
+
+    /// The blocks nested in the statement.
+    pub fn blocks(&self) -> Vec<&Block> {
+        match self {
+            Stmt::If {
+                then, otherwise, ..
+            }
+            | Stmt::IfLet {
+                then, otherwise, ..
+            } => vec![then, otherwise],
+            Stmt::Match { arms, .. } => arms.iter().map(|x| &x.body).collect(),
+            Stmt::Loop(body) => vec![body],
+            _ => vec![],
+        }
+    }
+
+    pub fn blocks_mut(&mut self) -> Vec<&mut Block> {
+        match self {
+            Stmt::If {
+                then, otherwise, ..
+            }
+            | Stmt::IfLet {
+                then, otherwise, ..
+            } => vec![then, otherwise],
+            Stmt::Match { arms, .. } => arms.iter_mut().map(|x| &mut x.body).collect(),
+            Stmt::Loop(body) => vec![body],
+            _ => vec![],
+        }
+    }
+
+    /// Calls `f` on every variable the statement reads, binds, declares or assigns, including
+    /// the ones of its blocks.
+    pub fn visit_vars_mut(&mut self, f: &mut impl FnMut(&mut Var)) {
+        match self {
+            Stmt::Declare { var, .. } => f(var),
+            Stmt::Let { pattern, .. }
+            | Stmt::Assign { pattern, .. }
+            | Stmt::IfLet { pattern, .. } => pattern.visit_vars_mut(f),
+            Stmt::Match { arms, .. } => {
+                for arm in arms {
+                    arm.pattern.visit_vars_mut(f);
+                }
+            }
+            _ => {}
+        }
+        for expr in self.exprs_mut() {
+            expr.visit_vars_mut(f);
+        }
+        for block in self.blocks_mut() {
+            for stmt in block {
+                stmt.visit_vars_mut(f);
+            }
+        }
+    }
+
+    /// Sets the comment following the statement, if it can have one.
+    pub fn with_comment(mut self, text: impl Into<String>) -> Self {
+        if let Stmt::Let { comment, .. }
+        | Stmt::Assign { comment, .. }
+        | Stmt::Expr { comment, .. } = &mut self
+        {
+            *comment = Some(text.into());
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arm {
+    pub pattern: Pattern,
+    pub body: Block,
+}
+
+/// A decompiled function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    /// Printed above the signature as is.
+    pub comment: Option<String>,
+    pub name: String,
+    pub params: Vec<(Var, String)>,
+    pub ret_types: Vec<String>,
+    /// The types of the builtins left out of the parameters, listed after the return types.
+    pub implicits: Vec<String>,
+    pub body: Block,
+}
@@ -0,0 +1,624 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use cairo_lang_sierra::{
+    ids::VarId,
+    program::{GenStatement, StatementIdx},
+};
+
+pub type BlockId = usize;
+
+/// A straight-line run of statements, only the last one may branch or return.
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    pub start: StatementIdx,
+    /// The terminator statement, inclusive.
+    pub end: StatementIdx,
+    /// One successor per branch of the terminator, in branch order.
+    pub succs: Vec<BlockId>,
+    pub preds: Vec<BlockId>,
+}
+
+impl BasicBlock {
+    pub fn statements(&self) -> impl DoubleEndedIterator<Item = StatementIdx> {
+        (self.start.0..=self.end.0).map(StatementIdx)
+    }
+}
+
+/// The control flow graph of a single function.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+    entry: BlockId,
+    block_of: HashMap<StatementIdx, BlockId>,
+    idom: Vec<Option<BlockId>>,
+    ipdom: Vec<Option<BlockId>>,
+    join: Vec<Option<BlockId>>,
+    /// The body of the natural loop of every loop header, including the header.
+    loops: HashMap<BlockId, BTreeSet<BlockId>>,
+    live_in: Vec<HashSet<VarId>>,
+}
+
+impl Cfg {
+    pub fn new(statements: &[GenStatement<StatementIdx>], entry_point: StatementIdx) -> Self {
+        // Find all the statements reachable from the entry and which of them start a block.
+        let mut leaders = BTreeSet::from([entry_point]);
+        let mut reachable = BTreeSet::new();
+        let mut worklist = vec![entry_point];
+
+        while let Some(idx) = worklist.pop() {
+            if !reachable.insert(idx) {
+                continue;
+            }
+
+            if let GenStatement::Invocation(invocation) = &statements[idx.0] {
+                let is_terminator = invocation.branches.len() != 1
+                    || idx.next(&invocation.branches[0].target) != StatementIdx(idx.0 + 1);
+
+                for branch in &invocation.branches {
+                    let target = idx.next(&branch.target);
+                    if is_terminator {
+                        leaders.insert(target);
+                    }
+                    worklist.push(target);
+                }
+            }
+        }
+
+        let mut blocks = Vec::new();
+        let mut block_of = HashMap::new();
+
+        for &start in &leaders {
+            let block_id = blocks.len();
+            let mut end = start;
+
+            loop {
+                block_of.insert(end, block_id);
+
+                let next = StatementIdx(end.0 + 1);
+                let falls_through = match &statements[end.0] {
+                    GenStatement::Invocation(invocation) => {
+                        invocation.branches.len() == 1
+                            && end.next(&invocation.branches[0].target) == next
+                    }
+                    GenStatement::Return(_) => false,
+                };
+
+                if !falls_through || leaders.contains(&next) {
+                    break;
+                }
+                end = next;
+            }
+
+            blocks.push(BasicBlock {
+                start,
+                end,
+                succs: Vec::new(),
+                preds: Vec::new(),
+            });
+        }
+
+        for block_id in 0..blocks.len() {
+            let end = blocks[block_id].end;
+
+            if let GenStatement::Invocation(invocation) = &statements[end.0] {
+                for branch in &invocation.branches {
+                    let succ = block_of[&end.next(&branch.target)];
+                    blocks[block_id].succs.push(succ);
+                    if !blocks[succ].preds.contains(&block_id) {
+                        blocks[succ].preds.push(block_id);
+                    }
+                }
+            }
+        }
+
+        let succs: Vec<Vec<BlockId>> = blocks.iter().map(|b| b.succs.clone()).collect();
+        let entry = block_of[&entry_point];
+        let idom = compute_idoms(&succs, entry);
+
+        // Post dominators are the dominators of the reversed graph, with a virtual
+        // exit node joining every returning block.
+        let exit = blocks.len();
+        let mut rsuccs: Vec<Vec<BlockId>> = blocks.iter().map(|b| b.preds.clone()).collect();
+        rsuccs.push(
+            blocks
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| b.succs.is_empty())
+                .map(|(id, _)| id)
+                .collect(),
+        );
+        let mut ipdom = compute_idoms(&rsuccs, exit);
+        ipdom.pop();
+        for x in ipdom.iter_mut() {
+            if *x == Some(exit) {
+                *x = None;
+            }
+        }
+
+        let mut cfg = Self {
+            blocks,
+            entry,
+            block_of,
+            idom,
+            ipdom,
+            join: Vec::new(),
+            loops: HashMap::new(),
+            live_in: Vec::new(),
+        };
+        cfg.join = cfg.compute_joins();
+        cfg.loops = cfg.find_loops();
+        cfg.live_in = cfg.compute_liveness(statements);
+        cfg
+    }
+
+    /// Computes the join point of every branch, its immediate post dominator ignoring the
+    /// arms which only lead to a return or panic of their own. Those never merge back, so
+    /// any other arms still share the code after their join.
+    fn compute_joins(&self) -> Vec<Option<BlockId>> {
+        let n = self.blocks.len();
+
+        // A block is closed if it has a single predecessor and every edge leaving the
+        // blocks it dominates stays within them, the whole arm ends in its own returns.
+        let mut open = vec![false; n];
+        for (from, block) in self.blocks.iter().enumerate() {
+            for &to in &block.succs {
+                let mut current = Some(from);
+                while let Some(x) = current {
+                    if self.dominates(x, to) {
+                        break;
+                    }
+                    open[x] = true;
+                    current = self.idom[x];
+                }
+            }
+        }
+
+        // The innermost closed block dominating each block, the arm it belongs to.
+        let arm: Vec<Option<BlockId>> = (0..n)
+            .map(|x| {
+                let mut current = Some(x);
+                while let Some(x) = current {
+                    if !open[x] && self.blocks[x].preds.len() == 1 {
+                        return Some(x);
+                    }
+                    current = self.idom[x];
+                }
+                None
+            })
+            .collect();
+
+        // Post dominators of the graph without the closed arms, except the ones enclosing
+        // the branches of `arm`. The blocks left without successors reach the exit.
+        let exit = n;
+        let reduced = |arm_of_branch: Option<BlockId>| {
+            let excluded: Vec<bool> = (0..n)
+                .map(|x| match (arm[x], arm_of_branch) {
+                    (None, _) => false,
+                    (Some(arm), Some(enclosing)) => !self.dominates(arm, enclosing),
+                    (Some(_), None) => true,
+                })
+                .collect();
+
+            let mut rsuccs: Vec<Vec<BlockId>> = self
+                .blocks
+                .iter()
+                .map(|b| b.preds.iter().copied().filter(|&x| !excluded[x]).collect())
+                .collect();
+            rsuccs.push(
+                (0..n)
+                    .filter(|&x| !excluded[x] && self.blocks[x].succs.iter().all(|&y| excluded[y]))
+                    .collect(),
+            );
+            (excluded, compute_idoms(&rsuccs, exit))
+        };
+
+        let mut reduced_by_arm = HashMap::new();
+        (0..n)
+            .map(|x| {
+                if self.ipdom[x].is_some() || self.blocks[x].succs.len() < 2 {
+                    return self.ipdom[x];
+                }
+
+                let (excluded, ipdom) = reduced_by_arm
+                    .entry(arm[x])
+                    .or_insert_with(|| reduced(arm[x]));
+                let arms = self.blocks[x].succs.iter().filter(|&&y| !excluded[y]);
+                match arms.count() > 1 {
+                    true => ipdom[x].filter(|&x| x != exit),
+                    false => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Finds the natural loop of every back edge, merging the loops sharing a header.
+    fn find_loops(&self) -> HashMap<BlockId, BTreeSet<BlockId>> {
+        let mut loops: HashMap<BlockId, BTreeSet<BlockId>> = HashMap::new();
+
+        for (from, block) in self.blocks.iter().enumerate() {
+            for &header in &block.succs {
+                if !self.is_back_edge(from, header) {
+                    continue;
+                }
+
+                let body = loops
+                    .entry(header)
+                    .or_insert_with(|| BTreeSet::from([header]));
+                let mut worklist = vec![from];
+
+                while let Some(current) = worklist.pop() {
+                    if body.insert(current) {
+                        worklist.extend(self.blocks[current].preds.iter().copied());
+                    }
+                }
+            }
+        }
+
+        loops
+    }
+
+    /// Computes the variables live at the start of each block. The results of a branch
+    /// are only defined along the edge to its own successor.
+    fn compute_liveness(&self, statements: &[GenStatement<StatementIdx>]) -> Vec<HashSet<VarId>> {
+        let mut live_in = vec![HashSet::new(); self.blocks.len()];
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for (block_id, block) in self.blocks.iter().enumerate().rev() {
+                let mut live = HashSet::new();
+
+                for statement_idx in block.statements().rev() {
+                    match &statements[statement_idx.0] {
+                        GenStatement::Invocation(invocation) => {
+                            let mut next = HashSet::new();
+
+                            for (i, branch) in invocation.branches.iter().enumerate() {
+                                let branch_live = if statement_idx == block.end {
+                                    match block.succs.get(i) {
+                                        Some(&succ) => live_in[succ].clone(),
+                                        None => HashSet::new(),
+                                    }
+                                } else {
+                                    live.clone()
+                                };
+
+                                next.extend(
+                                    branch_live
+                                        .into_iter()
+                                        .filter(|var| !branch.results.contains(var)),
+                                );
+                            }
+
+                            next.extend(invocation.args.iter().cloned());
+                            live = next;
+                        }
+                        GenStatement::Return(vars) => {
+                            live = vars.iter().cloned().collect();
+                        }
+                    }
+                }
+
+                if live != live_in[block_id] {
+                    live_in[block_id] = live;
+                    changed = true;
+                }
+            }
+        }
+
+        live_in
+    }
+
+    pub fn entry(&self) -> BlockId {
+        self.entry
+    }
+
+    /// Returns the block containing the given statement, if it is reachable.
+    pub fn block_of(&self, idx: StatementIdx) -> Option<BlockId> {
+        self.block_of.get(&idx).copied()
+    }
+
+    /// The immediate dominator, `None` for the entry block.
+    pub fn idom(&self, block: BlockId) -> Option<BlockId> {
+        self.idom[block]
+    }
+
+    /// The immediate post dominator, `None` if the block only reaches the function exit.
+    pub fn ipdom(&self, block: BlockId) -> Option<BlockId> {
+        self.ipdom[block]
+    }
+
+    /// Where the branches of the block meet again, the immediate post dominator once the
+    /// arms ending in their own returns or panics are left out. `None` if at most one
+    /// arm continues.
+    pub fn join(&self, block: BlockId) -> Option<BlockId> {
+        self.join[block]
+    }
+
+    /// Whether every path from the entry to `b` goes through `a`.
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        let mut current = Some(b);
+        while let Some(x) = current {
+            if x == a {
+                return true;
+            }
+            current = self.idom[x];
+        }
+        false
+    }
+
+    /// Whether the edge `from -> to` jumps backwards to a block dominating its source.
+    pub fn is_back_edge(&self, from: BlockId, to: BlockId) -> bool {
+        self.dominates(to, from)
+    }
+
+    /// The blocks of the loop headed by `header`, if it is the target of a back edge.
+    pub fn loop_body(&self, header: BlockId) -> Option<&BTreeSet<BlockId>> {
+        self.loops.get(&header)
+    }
+
+    /// The variables whose values are needed at the start of the block.
+    pub fn live_in(&self, block: BlockId) -> &HashSet<VarId> {
+        &self.live_in[block]
+    }
+}
+
+/// Computes the immediate dominators of every node reachable from `entry` using
+/// the Cooper, Harvey and Kennedy iterative algorithm.
+fn compute_idoms(succs: &[Vec<usize>], entry: usize) -> Vec<Option<usize>> {
+    let n = succs.len();
+
+    // Reverse postorder numbering.
+    let mut postorder = Vec::with_capacity(n);
+    let mut visited = vec![false; n];
+    let mut stack = vec![(entry, 0)];
+    visited[entry] = true;
+    while let Some((node, i)) = stack.pop() {
+        if let Some(&succ) = succs[node].get(i) {
+            stack.push((node, i + 1));
+            if !visited[succ] {
+                visited[succ] = true;
+                stack.push((succ, 0));
+            }
+        } else {
+            postorder.push(node);
+        }
+    }
+
+    let mut order = vec![usize::MAX; n];
+    for (i, &node) in postorder.iter().enumerate() {
+        order[node] = i;
+    }
+
+    let mut preds = vec![Vec::new(); n];
+    for (node, node_succs) in succs.iter().enumerate() {
+        for &succ in node_succs {
+            preds[succ].push(node);
+        }
+    }
+
+    let mut idom: Vec<Option<usize>> = vec![None; n];
+    idom[entry] = Some(entry);
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for &node in postorder.iter().rev() {
+            if node == entry {
+                continue;
+            }
+
+            let mut new_idom = None;
+            for &pred in &preds[node] {
+                if idom[pred].is_none() {
+                    continue;
+                }
+                new_idom = Some(match new_idom {
+                    None => pred,
+                    Some(current) => intersect(&idom, &order, pred, current),
+                });
+            }
+
+            if new_idom.is_some() && idom[node] != new_idom {
+                idom[node] = new_idom;
+                changed = true;
+            }
+        }
+    }
+
+    idom[entry] = None;
+    idom
+}
+
+fn intersect(idom: &[Option<usize>], order: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while order[a] < order[b] {
+            a = idom[a].unwrap();
+        }
+        while order[b] < order[a] {
+            b = idom[b].unwrap();
+        }
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use cairo_lang_sierra::{
+        ids::ConcreteLibfuncId,
+        program::{BranchInfo, BranchTarget, Invocation},
+    };
+
+    use super::*;
+
+    /// An invocation reading `args`, with one branch per target statement and its results.
+    fn invoke(args: &[u64], branches: &[(usize, &[u64])]) -> GenStatement<StatementIdx> {
+        GenStatement::Invocation(Invocation {
+            libfunc_id: ConcreteLibfuncId::from_string("libfunc"),
+            args: args.iter().map(|&x| VarId::new(x)).collect(),
+            branches: branches
+                .iter()
+                .map(|(target, results)| BranchInfo {
+                    target: BranchTarget::Statement(StatementIdx(*target)),
+                    results: results.iter().map(|&x| VarId::new(x)).collect(),
+                })
+                .collect(),
+        })
+    }
+
+    fn jump(target: usize) -> GenStatement<StatementIdx> {
+        invoke(&[], &[(target, &[])])
+    }
+
+    fn branch(targets: &[usize]) -> GenStatement<StatementIdx> {
+        let branches: Vec<(usize, &[u64])> = targets.iter().map(|&x| (x, &[][..])).collect();
+        invoke(&[], &branches)
+    }
+
+    fn ret(vars: &[u64]) -> GenStatement<StatementIdx> {
+        GenStatement::Return(vars.iter().map(|&x| VarId::new(x)).collect())
+    }
+
+    fn vars(ids: &[u64]) -> HashSet<VarId> {
+        ids.iter().map(|&x| VarId::new(x)).collect()
+    }
+
+    #[test]
+    fn diamond() {
+        let cfg = Cfg::new(
+            &[branch(&[1, 2]), jump(3), jump(3), ret(&[])],
+            StatementIdx(0),
+        );
+
+        assert_eq!(cfg.blocks.len(), 4);
+        assert_eq!(cfg.blocks[0].succs, [1, 2]);
+        assert_eq!(cfg.blocks[3].preds, [1, 2]);
+        assert_eq!(
+            (0..4).map(|x| cfg.idom(x)).collect::<Vec<_>>(),
+            [None, Some(0), Some(0), Some(0)]
+        );
+        assert_eq!(
+            (0..4).map(|x| cfg.ipdom(x)).collect::<Vec<_>>(),
+            [Some(3), Some(3), Some(3), None]
+        );
+        assert_eq!(cfg.join(0), Some(3));
+        assert!((0..4).all(|x| cfg.loop_body(x).is_none()));
+    }
+
+    #[test]
+    fn diamond_with_exiting_arm() {
+        // The first arm branches again, either joining the second arm or returning.
+        let cfg = Cfg::new(
+            &[
+                branch(&[1, 4]),
+                branch(&[2, 3]),
+                jump(5),
+                ret(&[]),
+                jump(5),
+                ret(&[]),
+            ],
+            StatementIdx(0),
+        );
+
+        assert_eq!(cfg.ipdom(0), None);
+        assert_eq!(cfg.join(0), Some(5));
+        assert_eq!(cfg.ipdom(1), None);
+        assert_eq!(cfg.join(1), None);
+        assert_eq!(cfg.ipdom(2), Some(5));
+        assert_eq!(cfg.idom(5), Some(0));
+    }
+
+    #[test]
+    fn diamond_with_both_arms_exiting() {
+        let cfg = Cfg::new(&[branch(&[1, 2]), ret(&[]), ret(&[])], StatementIdx(0));
+
+        assert_eq!(cfg.ipdom(0), None);
+        assert_eq!(cfg.join(0), None);
+    }
+
+    #[test]
+    fn join_inside_exiting_arm() {
+        // The first arm of the entry only returns, but its own arms still join.
+        let cfg = Cfg::new(
+            &[
+                branch(&[1, 7]),
+                branch(&[2, 5]),
+                branch(&[3, 4]),
+                jump(6),
+                ret(&[]),
+                jump(6),
+                ret(&[]),
+                ret(&[]),
+            ],
+            StatementIdx(0),
+        );
+        let block = |x| cfg.block_of(StatementIdx(x)).unwrap();
+
+        assert_eq!(cfg.join(block(0)), None);
+        assert_eq!(cfg.ipdom(block(1)), None);
+        assert_eq!(cfg.join(block(1)), Some(block(6)));
+        assert_eq!(cfg.join(block(2)), None);
+    }
+
+    #[test]
+    fn nested_loop() {
+        // Statement 3 jumps back to the inner header, statement 4 to the outer one.
+        let cfg = Cfg::new(
+            &[
+                jump(1),
+                jump(2),
+                jump(3),
+                branch(&[2, 4]),
+                branch(&[1, 5]),
+                ret(&[]),
+            ],
+            StatementIdx(0),
+        );
+
+        let starts: Vec<usize> = cfg.blocks.iter().map(|x| x.start.0).collect();
+        assert_eq!(starts, [0, 1, 2, 4, 5]);
+        assert_eq!(cfg.blocks[2].end, StatementIdx(3));
+
+        assert_eq!(cfg.loop_body(1), Some(&BTreeSet::from([1, 2, 3])));
+        assert_eq!(cfg.loop_body(2), Some(&BTreeSet::from([2])));
+        assert_eq!(cfg.loop_body(0), None);
+        assert!(cfg.is_back_edge(2, 2));
+        assert!(cfg.is_back_edge(3, 1));
+        assert!(!cfg.is_back_edge(2, 3));
+        assert_eq!(cfg.ipdom(1), Some(2));
+        assert_eq!(cfg.ipdom(2), Some(3));
+        assert_eq!(cfg.ipdom(3), Some(4));
+    }
+
+    #[test]
+    fn unreachable_block() {
+        let cfg = Cfg::new(&[jump(2), ret(&[]), ret(&[])], StatementIdx(0));
+
+        assert_eq!(cfg.blocks.len(), 2);
+        assert_eq!(cfg.block_of(StatementIdx(1)), None);
+        assert_eq!(cfg.block_of(StatementIdx(2)), Some(1));
+        assert_eq!(cfg.blocks[0].succs, [1]);
+        assert_eq!(cfg.idom(1), Some(0));
+    }
+
+    #[test]
+    fn liveness() {
+        // The results of each branch are only defined along their own edge.
+        let cfg = Cfg::new(
+            &[
+                invoke(&[0], &[(1, &[1]), (2, &[2])]),
+                invoke(&[1, 9], &[(3, &[3])]),
+                invoke(&[2], &[(3, &[3])]),
+                ret(&[3]),
+            ],
+            StatementIdx(0),
+        );
+
+        assert_eq!(cfg.live_in(0), &vars(&[0, 9]));
+        assert_eq!(cfg.live_in(1), &vars(&[1, 9]));
+        assert_eq!(cfg.live_in(2), &vars(&[2]));
+        assert_eq!(cfg.live_in(3), &vars(&[3]));
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+
+use cairo_lang_sierra::{
+    debug_info::DebugInfo,
+    ids::{
+        ConcreteLibfuncId, ConcreteTypeId, FunctionId, GenericLibfuncId, GenericTypeId, UserTypeId,
+        VarId,
+    },
+    program::{
+        BranchInfo, BranchTarget, ConcreteLibfuncLongId, ConcreteTypeLongId, DeclaredTypeInfo,
+        Function, FunctionSignature, GenericArg, Invocation, LibfuncDeclaration, Param, Program,
+        Statement, StatementIdx, TypeDeclaration,
+    },
+};
+use cairo_lang_utils::bigint::BigUintAsHex;
+use num_bigint::{BigInt, BigUint};
+use num_traits::{One, ToPrimitive};
+use serde::Deserialize;
+use sha3::{Digest, Keccak256};
+
+use crate::Error;
+
+/// A Starknet contract class, as built by scarb or fetched from a node. The program is
+/// encoded as felts, see `cairo-lang-starknet-classes` for the encoder.
+#[derive(Debug, Deserialize)]
+pub struct ContractClass {
+    pub sierra_program: Vec<BigUintAsHex>,
+    #[serde(default)]
+    pub sierra_program_debug_info: Option<DebugInfo>,
+    pub entry_points_by_type: EntryPoints,
+    /// Either the ABI itself or a string holding it, as returned by the nodes.
+    #[serde(default)]
+    pub abi: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub struct EntryPoints {
+    #[serde(default)]
+    pub external: Vec<EntryPoint>,
+    #[serde(default)]
+    pub l1_handler: Vec<EntryPoint>,
+    #[serde(default)]
+    pub constructor: Vec<EntryPoint>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EntryPoint {
+    pub selector: BigUintAsHex,
+    pub function_idx: usize,
+}
+
+impl ContractClass {
+    /// Decodes the program, with the debug names of the class if it has them.
+    pub fn program(&self) -> Result<Program, Error> {
+        let felts: Vec<BigUint> = self
+            .sierra_program
+            .iter()
+            .map(|x| x.value.clone())
+            .collect();
+
+        // The sierra and compiler versions come first, three felts each.
+        let packed = felts.get(6..).ok_or(Error::InvalidContractClass(
+            "the sierra program is missing its versions",
+        ))?;
+        let felts = decompress(packed).ok_or(Error::InvalidContractClass(
+            "invalid compressed sierra program",
+        ))?;
+
+        let mut reader = FeltReader { felts: &felts };
+        let mut program = reader.program().ok_or(Error::InvalidContractClass(
+            "invalid felt252 encoding of the sierra program",
+        ))?;
+
+        if let Some(debug_info) = &self.sierra_program_debug_info {
+            debug_info.populate(&mut program);
+        }
+        Ok(program)
+    }
+
+    /// The ABI, which some classes hold as a JSON string.
+    fn abi(&self) -> Result<serde_json::Value, Error> {
+        Ok(match &self.abi {
+            Some(serde_json::Value::String(abi)) => serde_json::from_str(abi)?,
+            Some(abi) => abi.clone(),
+            None => serde_json::Value::Null,
+        })
+    }
+
+    /// A comment line for each entry point with its kind, ABI name and selector.
+    pub fn entry_point_comments(&self) -> Result<HashMap<FunctionId, String>, Error> {
+        let mut names = HashMap::new();
+        abi_names(&self.abi()?, &mut names);
+
+        let kinds = [
+            ("external", &self.entry_points_by_type.external),
+            ("l1_handler", &self.entry_points_by_type.l1_handler),
+            ("constructor", &self.entry_points_by_type.constructor),
+        ];
+
+        let mut comments = HashMap::new();
+        for (kind, entry_points) in kinds {
+            for entry_point in entry_points {
+                let selector = &entry_point.selector.value;
+                let comment = match names.get(selector) {
+                    Some(name) => format!("// {kind} `{name}`, selector: {selector:#x}\n"),
+                    None => format!("// {kind}, selector: {selector:#x}\n"),
+                };
+                comments.insert(FunctionId::new(entry_point.function_idx as u64), comment);
+            }
+        }
+
+        Ok(comments)
+    }
+
+    /// The names of the members of the structs declared in the ABI, by the path of the
+    /// struct.
+    pub fn struct_members(&self) -> Result<HashMap<String, Vec<String>>, Error> {
+        let mut structs = HashMap::new();
+        let abi = self.abi()?;
+
+        for item in abi.as_array().into_iter().flatten() {
+            if item["type"].as_str() != Some("struct") {
+                continue;
+            }
+            let (Some(name), Some(members)) = (item["name"].as_str(), item["members"].as_array())
+            else {
+                continue;
+            };
+            let members: Option<Vec<String>> = members
+                .iter()
+                .map(|x| x["name"].as_str().map(str::to_string))
+                .collect();
+            if let Some(members) = members {
+                structs.insert(name.to_string(), members);
+            }
+        }
+
+        Ok(structs)
+    }
+}
+
+/// Collects the names of the entry points declared in the ABI by their selectors.
+fn abi_names(abi: &serde_json::Value, names: &mut HashMap<BigUint, String>) {
+    let Some(items) = abi.as_array() else {
+        return;
+    };
+
+    for item in items {
+        match item["type"].as_str() {
+            Some("function" | "l1_handler" | "constructor") => {
+                if let Some(name) = item["name"].as_str() {
+                    names.insert(starknet_keccak(name.as_bytes()), name.to_string());
+                }
+            }
+            Some("interface") => abi_names(&item["items"], names),
+            _ => {}
+        }
+    }
+}
+
+/// The keccak of the data truncated to 250 bits, which selectors and long ids are.
+fn starknet_keccak(data: &[u8]) -> BigUint {
+    let mut hash = Keccak256::digest(data);
+    hash[0] &= 3;
+    BigUint::from_bytes_be(&hash)
+}
+
+/// The generic ids longer than a short string, which are encoded as their keccak.
+const LONG_IDS: &[&str] = &[
+    "storage_address_from_base_and_offset",
+    "contract_address_try_from_felt252",
+    "storage_base_address_from_felt252",
+    "storage_address_try_from_felt252",
+    "secp256k1_get_point_from_x_syscall",
+    "secp256r1_get_point_from_x_syscall",
+    "circuit_failure_guarantee_verify",
+    "u96_limbs_less_than_guarantee_verify",
+    "u96_single_limb_less_than_guarantee_verify",
+];
+
+/// Unpacks the felts, which hold indices into a code of the distinct values as many
+/// digits as fit in a felt.
+fn decompress(packed: &[BigUint]) -> Option<Vec<BigUint>> {
+    let (code_size, packed) = packed.split_first()?;
+    let code_size = code_size.to_usize()?;
+    let (padding_size, packed) = packed.split_first()?;
+    let padding_size = padding_size.to_usize()?;
+    if code_size >= packed.len() {
+        return None;
+    }
+    let (code, packed) = packed.split_at(code_size);
+    let (unpacked_size, packed) = packed.split_first()?;
+    let mut remaining = unpacked_size.to_usize()?;
+
+    let base = BigUint::from(code_size + padding_size);
+    let prime = (BigUint::one() << 251) + (BigUint::from(17u8) << 192) + BigUint::one();
+    let mut digits_per_felt = 0;
+    let mut max_encoded = base.clone();
+    while max_encoded < prime {
+        max_encoded *= &base;
+        digits_per_felt += 1;
+    }
+
+    let mut felts = Vec::with_capacity(remaining);
+    for value in packed {
+        let mut value = value.clone();
+        for _ in 0..digits_per_felt.min(remaining) {
+            let digit = (&value % &base).to_usize()?;
+            felts.push(code.get(digit)?.clone());
+            value /= &base;
+        }
+        remaining -= digits_per_felt.min(remaining);
+    }
+
+    (remaining == 0).then_some(felts)
+}
+
+/// Reads a program from its felt encoding, in the order it was written.
+struct FeltReader<'a> {
+    felts: &'a [BigUint],
+}
+
+impl FeltReader<'_> {
+    fn felt(&mut self) -> Option<BigUint> {
+        let (felt, rest) = self.felts.split_first()?;
+        self.felts = rest;
+        Some(felt.clone())
+    }
+
+    fn usize(&mut self) -> Option<usize> {
+        self.felt()?.to_usize()
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.felt()?.to_u64()
+    }
+
+    /// Reads a length prefixed list.
+    fn list<T>(&mut self, read: impl Fn(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        let len = self.usize()?;
+        if len > self.felts.len() {
+            return None;
+        }
+        (0..len).map(|_| read(self)).collect()
+    }
+
+    /// Reads a generic id, a short string unless it is one of the long ids.
+    fn generic_id(&mut self) -> Option<String> {
+        let felt = self.felt()?;
+        if let Some(id) = LONG_IDS
+            .iter()
+            .find(|id| starknet_keccak(id.as_bytes()) == felt)
+        {
+            return Some(id.to_string());
+        }
+        String::from_utf8(felt.to_bytes_be()).ok()
+    }
+
+    fn var(&mut self) -> Option<VarId> {
+        Some(VarId::new(self.u64()?))
+    }
+
+    fn ty(&mut self) -> Option<ConcreteTypeId> {
+        Some(ConcreteTypeId::new(self.u64()?))
+    }
+
+    fn generic_arg(&mut self) -> Option<GenericArg> {
+        Some(match self.usize()? {
+            0 => GenericArg::UserType(UserTypeId {
+                id: self.felt()?,
+                debug_name: None,
+            }),
+            1 => GenericArg::Type(self.ty()?),
+            2 => GenericArg::Value(self.felt()?.into()),
+            3 => GenericArg::UserFunc(FunctionId::new(self.u64()?)),
+            4 => GenericArg::Libfunc(ConcreteLibfuncId::new(self.u64()?)),
+            5 => GenericArg::Value(-BigInt::from(self.felt()?)),
+            _ => return None,
+        })
+    }
+
+    /// Reads a type declaration, whose generic argument count shares a felt with the
+    /// type info flags above the low 128 bits. Declared type infos set the marker bit, so
+    /// the ones whose flags are all false are kept too.
+    fn type_declaration(&mut self, id: usize) -> Option<TypeDeclaration> {
+        const TYPE_INFO_MARKER: u64 = 1 << 63;
+
+        let generic_id = GenericTypeId::from_string(self.generic_id()?);
+        let len_and_info = self.felt()?;
+        let len = (&len_and_info & BigUint::from(u128::MAX)).to_usize()?;
+        let info = (len_and_info >> 128u32).to_u64()?;
+        let generic_args = (0..len)
+            .map(|_| self.generic_arg())
+            .collect::<Option<_>>()?;
+
+        Some(TypeDeclaration {
+            id: ConcreteTypeId::new(id as u64),
+            long_id: ConcreteTypeLongId {
+                generic_id,
+                generic_args,
+            },
+            declared_type_info: (info & TYPE_INFO_MARKER != 0).then_some(DeclaredTypeInfo {
+                storable: info & 0b0001 != 0,
+                droppable: info & 0b0010 != 0,
+                duplicatable: info & 0b0100 != 0,
+                zero_sized: info & 0b1000 != 0,
+            }),
+        })
+    }
+
+    fn libfunc_declaration(&mut self, id: usize) -> Option<LibfuncDeclaration> {
+        Some(LibfuncDeclaration {
+            id: ConcreteLibfuncId::new(id as u64),
+            long_id: ConcreteLibfuncLongId {
+                generic_id: GenericLibfuncId::from_string(self.generic_id()?),
+                generic_args: self.list(Self::generic_arg)?,
+            },
+        })
+    }
+
+    fn statement(&mut self) -> Option<Statement> {
+        Some(match self.u64()? {
+            0 => Statement::Invocation(Invocation {
+                libfunc_id: ConcreteLibfuncId::new(self.u64()?),
+                args: self.list(Self::var)?,
+                branches: self.list(|reader| {
+                    let target = match reader.usize()? {
+                        usize::MAX => BranchTarget::Fallthrough,
+                        idx => BranchTarget::Statement(StatementIdx(idx)),
+                    };
+                    Some(BranchInfo {
+                        target,
+                        results: reader.list(Self::var)?,
+                    })
+                })?,
+            }),
+            1 => Statement::Return(self.list(Self::var)?),
+            _ => return None,
+        })
+    }
+
+    fn function(&mut self, id: usize) -> Option<Function> {
+        let signature = FunctionSignature {
+            param_types: self.list(Self::ty)?,
+            ret_types: self.list(Self::ty)?,
+        };
+        let params = signature
+            .param_types
+            .iter()
+            .map(|ty| {
+                Some(Param {
+                    id: self.var()?,
+                    ty: ty.clone(),
+                })
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Function {
+            id: FunctionId::new(id as u64),
+            signature,
+            params,
+            entry_point: StatementIdx(self.usize()?),
+        })
+    }
+
+    fn program(&mut self) -> Option<Program> {
+        let len = self.usize()?;
+        let type_declarations = (0..len)
+            .map(|i| self.type_declaration(i))
+            .collect::<Option<_>>()?;
+        let len = self.usize()?;
+        let libfunc_declarations = (0..len)
+            .map(|i| self.libfunc_declaration(i))
+            .collect::<Option<_>>()?;
+        let statements = self.list(Self::statement)?;
+        let len = self.usize()?;
+        let funcs = (0..len).map(|i| self.function(i)).collect::<Option<_>>()?;
+
+        Some(Program {
+            type_declarations,
+            libfunc_declarations,
+            statements,
+            funcs,
+        })
+    }
+}
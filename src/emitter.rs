@@ -0,0 +1,354 @@
+use cairo_lang_sierra::{
+    extensions::{core::CoreTypeConcrete, ConcreteLibfunc},
+    ids::{ConcreteTypeId, VarId},
+    program::{Invocation, StatementIdx},
+};
+use num_bigint::BigInt;
+
+use crate::{
+    ast::{Arm, BinaryOp, Block, Expr, Pattern, Stmt, Var},
+    bind, build_branch,
+    cfg::BlockId,
+    declare, get_libfunc_name, hoist_join_variables, Error, FunctionContext, FunctionState,
+};
+
+/// Emits the libfuncs which mostly differ in the expression they are shown as. Their
+/// leading arguments may be builtins, which are rebound to the matching results.
+pub struct LibfuncEmitter<'a> {
+    pub ctx: &'a FunctionContext<'a>,
+    pub statement_idx: StatementIdx,
+    pub invocation: &'a Invocation,
+    /// How many of the leading arguments and results are builtins.
+    pub builtins: usize,
+}
+
+impl<'a> LibfuncEmitter<'a> {
+    pub fn new(
+        ctx: &'a FunctionContext<'a>,
+        statement_idx: StatementIdx,
+        invocation: &'a Invocation,
+    ) -> Self {
+        Self {
+            ctx,
+            statement_idx,
+            invocation,
+            builtins: 1,
+        }
+    }
+
+    pub fn with_builtins(mut self, builtins: usize) -> Self {
+        self.builtins = builtins;
+        self
+    }
+
+    /// The types of the results of the given branch.
+    pub fn result_tys(&self, branch: usize) -> Result<Vec<ConcreteTypeId>, Error> {
+        Ok(self
+            .ctx
+            .reg
+            .get_libfunc(&self.invocation.libfunc_id)?
+            .branch_signatures()[branch]
+            .vars
+            .iter()
+            .map(|x| x.ty.clone())
+            .collect())
+    }
+
+    /// The results of the given branch after the first `skip` ones and their types. The
+    /// multiplication guarantees are left out, they only serve to verify the arithmetic, and
+    /// so are the implicit builtins.
+    pub fn values(
+        &self,
+        branch: usize,
+        skip: usize,
+    ) -> Result<(Vec<VarId>, Vec<ConcreteTypeId>), Error> {
+        let mut values = (Vec::new(), Vec::new());
+
+        for (var, ty) in self.invocation.branches[branch].results[skip..]
+            .iter()
+            .zip(&self.result_tys(branch)?[skip..])
+        {
+            if !matches!(
+                self.ctx.reg.get_type(ty)?,
+                CoreTypeConcrete::Uint128MulGuarantee(_)
+            ) && !self.ctx.is_implicit(ty)?
+            {
+                values.0.push(var.clone());
+                values.1.push(ty.clone());
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn libfunc_name(&self) -> String {
+        get_libfunc_name(self.ctx, &self.invocation.libfunc_id)
+    }
+
+    /// The i-th argument, counting the builtins.
+    pub fn arg(&self, i: usize) -> Expr {
+        (&self.invocation.args[i]).into()
+    }
+
+    /// The arguments after the first `skip` ones, which are the builtins, leaving out the
+    /// implicit builtins among the rest.
+    pub fn args(&self, skip: usize) -> Result<Vec<Expr>, Error> {
+        let params = self
+            .ctx
+            .reg
+            .get_libfunc(&self.invocation.libfunc_id)?
+            .param_signatures();
+
+        let mut args = Vec::new();
+        for (arg, param) in self.invocation.args.iter().zip(params).skip(skip) {
+            if !self.ctx.is_implicit(&param.ty)? {
+                args.push(arg.into());
+            }
+        }
+        Ok(args)
+    }
+
+    /// Applies `op` to the first two arguments after the builtins.
+    pub fn binary(&self, op: BinaryOp) -> Expr {
+        Expr::binary(self.arg(self.builtins), op, self.arg(self.builtins + 1))
+    }
+
+    /// Rebinds the builtins passed as the leading arguments to their results in `branch`.
+    pub fn rebind_builtins(
+        &self,
+        body: &mut Block,
+        state: &FunctionState,
+        branch: usize,
+    ) -> Result<(), Error> {
+        let builtins = self.invocation.branches[branch]
+            .results
+            .iter()
+            .zip(self.result_tys(branch)?)
+            .zip(&self.invocation.args)
+            .take(self.builtins);
+
+        for ((builtin, ty), arg) in builtins {
+            if !self.ctx.is_implicit(&ty)? {
+                body.push(declare(state, self.ctx, builtin, &ty, false, arg.into())?);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn build_const(
+        &self,
+        body: &mut Block,
+        state: &FunctionState,
+        value: BigInt,
+    ) -> Result<(), Error> {
+        let results = &self.invocation.branches[0].results;
+        bind(
+            body,
+            state,
+            self.ctx,
+            results,
+            &self.result_tys(0)?,
+            Expr::literal(value),
+        )
+    }
+
+    /// Emits an expression of the arguments, without builtins.
+    pub fn build_expr(
+        &self,
+        body: &mut Block,
+        state: &FunctionState,
+        expr: Expr,
+    ) -> Result<(), Error> {
+        let (vars, tys) = self.values(0, 0)?;
+        bind(body, state, self.ctx, &vars, &tys, expr)
+    }
+
+    /// Emits an expression of the arguments after the leading builtins, which are rebound.
+    pub fn build_builtin_expr(
+        &self,
+        body: &mut Block,
+        state: &FunctionState,
+        expr: Expr,
+    ) -> Result<(), Error> {
+        self.rebind_builtins(body, state, 0)?;
+
+        let (vars, tys) = self.values(0, self.builtins)?;
+        bind(body, state, self.ctx, &vars, &tys, expr)
+    }
+
+    /// Emits a libfunc without an operator as a call to it.
+    pub fn build_call(&self, body: &mut Block, state: &FunctionState) -> Result<(), Error> {
+        self.build_expr(body, state, self.call(&self.libfunc_name(), 0)?)
+    }
+
+    /// Emits a libfunc without an operator taking leading builtins as a call to it.
+    pub fn build_builtin_call(&self, body: &mut Block, state: &FunctionState) -> Result<(), Error> {
+        self.build_builtin_expr(body, state, self.call(&self.libfunc_name(), self.builtins)?)
+    }
+
+    /// A call to `name` with the arguments after the first `skip` ones.
+    pub fn call(&self, name: &str, skip: usize) -> Result<Expr, Error> {
+        Ok(Expr::call(name, self.args(skip)?))
+    }
+
+    /// Emits an equality check, whose first branch is taken when the values differ.
+    pub fn build_equal(&self, body: &mut Block, state: &mut FunctionState) -> Result<(), Error> {
+        let block = self.block();
+
+        hoist_join_variables(body, self.ctx, block, &[], state)?;
+        let mut then = Block::new();
+        build_branch(&mut then, self.ctx, block, 1, state)?;
+        let mut otherwise = Block::new();
+        build_branch(&mut otherwise, self.ctx, block, 0, state)?;
+
+        body.push(Stmt::If {
+            cond: Expr::binary(self.arg(0), BinaryOp::Eq, self.arg(1)),
+            then,
+            otherwise,
+        });
+        Ok(())
+    }
+
+    /// Emits a fallible conversion from a felt252 as an `if let`.
+    pub fn build_try_from_felt252(
+        &self,
+        body: &mut Block,
+        state: &mut FunctionState,
+    ) -> Result<(), Error> {
+        let block = self.block();
+        let value = &self.invocation.branches[0].results[1];
+
+        hoist_join_variables(body, self.ctx, block, &[], state)?;
+        let pattern = Pattern::Variant {
+            path: "Some".to_string(),
+            value: Some(Box::new(self.pattern_binding(state, value).into())),
+        };
+
+        let mut then = Block::new();
+        self.assign_pattern(&mut then, state, &[value]);
+        self.rebind_builtins(&mut then, state, 0)?;
+        build_branch(&mut then, self.ctx, block, 0, state)?;
+
+        let mut otherwise = Block::new();
+        self.rebind_builtins(&mut otherwise, state, 1)?;
+        build_branch(&mut otherwise, self.ctx, block, 1, state)?;
+
+        body.push(Stmt::IfLet {
+            pattern,
+            expr: Expr::method(self.arg(self.builtins), "try_into", vec![]),
+            then,
+            otherwise,
+        });
+        Ok(())
+    }
+
+    /// Emits a branching libfunc taking leading builtins as a match on `expr`, with one arm
+    /// per branch. A pattern ending in `({})` binds the values of its branch in place of
+    /// the `{}`, other patterns are variants without a value.
+    pub fn build_builtin_match(
+        &self,
+        body: &mut Block,
+        state: &mut FunctionState,
+        expr: Expr,
+        patterns: &[&str],
+    ) -> Result<(), Error> {
+        let block = self.block();
+
+        hoist_join_variables(body, self.ctx, block, &[], state)?;
+
+        let mut arms = Vec::new();
+        for (branch, pattern) in patterns.iter().enumerate() {
+            let (values, _) = self.values(branch, self.builtins)?;
+            let values: Vec<&VarId> = values.iter().collect();
+            let mut bindings: Vec<Pattern> = values
+                .iter()
+                .map(|x| self.pattern_binding(state, x).into())
+                .collect();
+            let pattern = match pattern.strip_suffix("({})") {
+                Some(path) => Pattern::Variant {
+                    path: path.to_string(),
+                    value: Some(Box::new(match bindings.len() {
+                        1 => bindings.remove(0),
+                        _ => Pattern::Tuple(bindings),
+                    })),
+                },
+                None => Pattern::Variant {
+                    path: pattern.to_string(),
+                    value: None,
+                },
+            };
+
+            let mut arm_body = Block::new();
+            self.assign_pattern(&mut arm_body, state, &values);
+            self.rebind_builtins(&mut arm_body, state, branch)?;
+            build_branch(&mut arm_body, self.ctx, block, branch, state)?;
+            arms.push(Arm {
+                pattern,
+                body: arm_body,
+            });
+        }
+
+        body.push(Stmt::Match { expr, arms });
+        Ok(())
+    }
+
+    /// Emits a libfunc which isn't decompiled yet as a plain call to it, or as a match on
+    /// its branches if it has several, so the rest of the function is still decompiled.
+    pub fn build_unsupported(
+        &self,
+        body: &mut Block,
+        state: &mut FunctionState,
+    ) -> Result<(), Error> {
+        let name = self.libfunc_name();
+        let call = self.call(&name, self.builtins)?;
+        state.unsupported.push(name);
+
+        if let [_] = &self.invocation.branches[..] {
+            let mut stmts = Block::new();
+            self.build_expr(&mut stmts, state, call)?;
+            if let Some(stmt) = stmts.pop() {
+                stmts.push(stmt.with_comment("unsupported"));
+            }
+            body.extend(stmts);
+            return Ok(());
+        }
+
+        let patterns: Vec<String> = (0..self.invocation.branches.len())
+            .map(|branch| format!("Branch{branch}({{}})"))
+            .collect();
+        let patterns: Vec<&str> = patterns.iter().map(|x| x.as_str()).collect();
+
+        body.push(Stmt::Comment("unsupported".to_string()));
+        self.build_builtin_match(body, state, call, &patterns)
+    }
+
+    /// The variable a pattern binds `var` to, a placeholder if it was hoisted.
+    pub fn pattern_binding(&self, state: &FunctionState, var: &VarId) -> Var {
+        if state.hoisted.contains(var) {
+            Var::Value(var.id)
+        } else {
+            var.into()
+        }
+    }
+
+    /// Assigns the hoisted variables bound by a pattern.
+    pub fn assign_pattern(&self, body: &mut Block, state: &FunctionState, vars: &[&VarId]) {
+        for var in vars {
+            if state.hoisted.contains(*var) {
+                body.push(Stmt::bind(
+                    true,
+                    (*var).into(),
+                    None,
+                    Var::Value(var.id).into(),
+                ));
+            }
+        }
+    }
+
+    pub fn block(&self) -> BlockId {
+        self.ctx
+            .cfg
+            .block_of(self.statement_idx)
+            .expect("statement should be reachable")
+    }
+}
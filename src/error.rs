@@ -0,0 +1,60 @@
+use std::fmt;
+
+use cairo_lang_sierra::{ids::FunctionId, program_registry::ProgramRegistryError};
+
+/// The errors of loading and decompiling a program.
+#[derive(Debug)]
+pub enum Error {
+    /// The input isn't valid textual Sierra.
+    Parse(String),
+    /// The input isn't valid JSON, or isn't any of the supported JSON formats.
+    Json(serde_json::Error),
+    /// The JSON is neither a `VersionedProgram` nor a contract class.
+    UnknownFormat,
+    /// The felt encoding of the program of a contract class is invalid.
+    InvalidContractClass(&'static str),
+    /// The program uses types or libfuncs inconsistently.
+    Registry(Box<ProgramRegistryError>),
+    /// The program has no function with the id.
+    MissingFunction(FunctionId),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(error) => write!(f, "invalid sierra: {error}"),
+            Error::Json(error) => write!(f, "invalid JSON: {error}"),
+            Error::UnknownFormat => {
+                write!(
+                    f,
+                    "the JSON is neither a versioned program nor a contract class"
+                )
+            }
+            Error::InvalidContractClass(error) => write!(f, "invalid contract class: {error}"),
+            Error::Registry(error) => write!(f, "invalid program: {error}"),
+            Error::MissingFunction(id) => write!(f, "no function {id} in the program"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Json(error) => Some(error),
+            Error::Registry(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Json(error)
+    }
+}
+
+impl From<Box<ProgramRegistryError>> for Error {
+    fn from(error: Box<ProgramRegistryError>) -> Self {
+        Error::Registry(error)
+    }
+}
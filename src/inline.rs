@@ -0,0 +1,222 @@
+//! Inlining of the loop helpers, the functions calling themselves as a tail call which the
+//! compiler lowers loops into, as a `loop` at their call sites.
+
+use std::collections::{HashMap, HashSet};
+
+use cairo_lang_sierra::{
+    ids::FunctionId,
+    program::{GenStatement, Invocation},
+};
+
+use crate::{
+    ast::{Block, Expr, Pattern, Stmt, Var},
+    build_loop_function, get_function_name, get_type_name, get_variant_name,
+    panic::panic_result_values,
+    self_tail_calls, Error, FunctionContext, FunctionState,
+};
+
+/// Emits a call to a loop helper as its loop, see [`build_loop_function`]. The parameters
+/// are bound to the arguments before the loop, and the returns of the helper assign the
+/// results of the call and break out of it.
+///
+/// Returns false without emitting anything if `callee` isn't a loop helper, or is already
+/// being inlined.
+pub(crate) fn build_inlined_loop(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    invocation: &Invocation,
+    callee: &FunctionId,
+    state: &mut FunctionState,
+) -> Result<bool, Error> {
+    let decompiler = ctx.decompiler;
+    if decompiler.keep_loop_calls || *callee == ctx.function.id || ctx.callers.contains(callee) {
+        return Ok(false);
+    }
+    let Some(func) = decompiler.program.funcs.iter().find(|x| x.id == *callee) else {
+        return Ok(false);
+    };
+
+    let mut callers = ctx.callers.clone();
+    callers.push(ctx.function.id.clone());
+    let helper = decompiler.context(func, callers)?;
+    let tail_calls = self_tail_calls(&helper)?;
+    if tail_calls.is_empty() {
+        return Ok(false);
+    }
+
+    let mut helper_state = FunctionState::default();
+    let (mutable, mut loop_body) = build_loop_function(&helper, &tail_calls, &mut helper_state)?;
+
+    // The ids of the helper are given to other variables of the caller, rename them.
+    let mut taken = caller_ids(ctx);
+    taken.extend(&state.inlined);
+    let renames = rename_vars(&mut loop_body, &helper, &mut taken);
+    let rename = |id: u64| renames.get(&id).copied().unwrap_or(id);
+
+    let mut results = Vec::new();
+    for (var, ty) in invocation.branches[0]
+        .results
+        .iter()
+        .zip(&func.signature.ret_types)
+    {
+        if !ctx.is_implicit(ty)? {
+            results.push((var, ty));
+        }
+    }
+
+    // The helper returns the values wrapped by a `PanicResult::Ok`, the call the result.
+    let ok = match func.signature.ret_types.last() {
+        Some(ty) if panic_result_values(ctx.reg, ty)?.is_some() => {
+            Some(get_variant_name(ctx, ty, 0))
+        }
+        _ => None,
+    };
+    let pattern: Vec<Pattern> = results.iter().map(|(var, _)| Pattern::from(*var)).collect();
+    if !break_on_return(&mut loop_body, &pattern, ok.as_deref(), false) {
+        return Ok(false);
+    }
+
+    body.push(Stmt::Comment(format!(
+        "{} inlined as a loop",
+        get_function_name(ctx, callee)
+    )));
+    for (var, ty) in &results {
+        if !state.hoisted.contains(*var) {
+            body.push(Stmt::Declare {
+                mutable: true,
+                var: (*var).into(),
+                ty: get_type_name(ctx, ty)?,
+            });
+        }
+    }
+    for (param, arg) in func.params.iter().zip(&invocation.args) {
+        if !ctx.is_implicit(&param.ty)? {
+            body.push(Stmt::Let {
+                mutable: mutable.contains(&param.id),
+                pattern: Pattern::Var(Var::Sierra(rename(param.id.id))),
+                ty: Some(get_type_name(ctx, &param.ty)?),
+                expr: arg.into(),
+                comment: None,
+            });
+        }
+    }
+    body.push(Stmt::Loop(loop_body));
+
+    state.inlined.extend(renames.values());
+    state.unsupported.extend(helper_state.unsupported);
+
+    Ok(true)
+}
+
+/// The ids of the variables of the function being decompiled.
+fn caller_ids(ctx: &FunctionContext) -> HashSet<u64> {
+    let mut ids: HashSet<u64> = ctx.function.params.iter().map(|x| x.id.id).collect();
+
+    for statement_idx in ctx.cfg.blocks.iter().flat_map(|x| x.statements()) {
+        match &ctx.statements[statement_idx.0] {
+            GenStatement::Invocation(invocation) => {
+                ids.extend(invocation.args.iter().map(|x| x.id));
+                for branch in &invocation.branches {
+                    ids.extend(branch.results.iter().map(|x| x.id));
+                }
+            }
+            GenStatement::Return(vars) => ids.extend(vars.iter().map(|x| x.id)),
+        }
+    }
+
+    ids
+}
+
+/// Gives the variables of the helper which are `taken` the next free id, in the block and
+/// the parameters. Returns the ids of the helper variables with their new id.
+fn rename_vars(
+    block: &mut Block,
+    helper: &FunctionContext,
+    taken: &mut HashSet<u64>,
+) -> HashMap<u64, u64> {
+    let mut ids: HashSet<u64> = helper.function.params.iter().map(|x| x.id.id).collect();
+    for stmt in block.iter_mut() {
+        stmt.visit_vars_mut(&mut |var| {
+            ids.insert(id(var));
+        });
+    }
+    let mut ids: Vec<u64> = ids.into_iter().collect();
+    ids.sort();
+
+    // The ids which are free are kept first, so that no other variable is renamed to them.
+    let (clashing, kept): (Vec<u64>, Vec<u64>) = ids.into_iter().partition(|x| taken.contains(x));
+    let mut renames: HashMap<u64, u64> = kept.into_iter().map(|x| (x, x)).collect();
+    taken.extend(renames.keys());
+    for old in clashing {
+        let mut new = old;
+        while taken.contains(&new) {
+            new = new.wrapping_add(1);
+        }
+        taken.insert(new);
+        renames.insert(old, new);
+    }
+
+    for stmt in block.iter_mut() {
+        stmt.visit_vars_mut(&mut |var| {
+            *var = match *var {
+                Var::Sierra(id) => Var::Sierra(renames[&id]),
+                Var::Overflowed(id) => Var::Overflowed(renames[&id]),
+                Var::Value(id) => Var::Value(renames[&id]),
+            }
+        });
+    }
+
+    renames
+}
+
+/// The id of the Sierra variable `var` stands for.
+fn id(var: &Var) -> u64 {
+    match *var {
+        Var::Sierra(id) | Var::Overflowed(id) | Var::Value(id) => id,
+    }
+}
+
+/// Replaces the returns of the block by an assignment of the values to `results` and a
+/// `break`, the last value wrapped in the `ok` variant if there is one. Returns false if a
+/// return is in a nested loop, which the `break` wouldn't leave, or doesn't match `results`.
+fn break_on_return(block: &mut Block, results: &[Pattern], ok: Option<&str>, nested: bool) -> bool {
+    for mut stmt in std::mem::take(block) {
+        let Stmt::Return(mut values) = stmt else {
+            let nested = nested || matches!(stmt, Stmt::Loop(_));
+            for inner in stmt.blocks_mut() {
+                if !break_on_return(inner, results, ok, nested) {
+                    return false;
+                }
+            }
+            block.push(stmt);
+            continue;
+        };
+        if nested || values.len() != results.len() {
+            return false;
+        }
+
+        if let Some(ok) = ok {
+            let last = match values.pop() {
+                // Already the result, propagated by `?` when returned.
+                Some(Expr::Try(result)) => *result,
+                Some(value) => Expr::call(ok, vec![value]),
+                None => return false,
+            };
+            values.push(last);
+        }
+
+        match values.len() {
+            0 => {}
+            1 => block.push(Stmt::bind(true, results[0].clone(), None, values.remove(0))),
+            _ => block.push(Stmt::bind(
+                true,
+                Pattern::Tuple(results.to_vec()),
+                None,
+                Expr::Tuple(values),
+            )),
+        }
+        block.push(Stmt::Break);
+    }
+
+    true
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use cairo_lang_sierra::{
+    ids::FunctionId,
+    program::{Program, VersionedProgram},
+    ProgramParser,
+};
+
+use crate::{contract_class::ContractClass, Error};
+
+/// A program loaded from any of the supported inputs.
+pub struct Input {
+    pub program: Program,
+    /// A comment to print above each entry point of a contract.
+    pub entry_points: HashMap<FunctionId, String>,
+    /// The names of the members of the structs in the ABI of a contract, by their path.
+    pub struct_members: HashMap<String, Vec<String>>,
+}
+
+/// Loads a program from textual Sierra, the `VersionedProgram` JSON emitted by scarb
+/// and `cairo-compile`, or a contract class, whichever `src` holds.
+pub fn load(src: &str) -> Result<Input, Error> {
+    if !src.trim_start().starts_with('{') {
+        return Ok(Input {
+            program: parse_program(src)?,
+            entry_points: HashMap::new(),
+            struct_members: HashMap::new(),
+        });
+    }
+
+    let json: serde_json::Value = serde_json::from_str(src)?;
+
+    if json.get("sierra_program").is_some() {
+        let class: ContractClass = serde_json::from_value(json)?;
+        Ok(Input {
+            program: class.program()?,
+            entry_points: class.entry_point_comments()?,
+            struct_members: class.struct_members()?,
+        })
+    } else if json.get("version").is_some() {
+        let VersionedProgram::V1 {
+            program: artifact, ..
+        } = serde_json::from_value(json)?;
+        let mut program = artifact.program;
+        if let Some(debug_info) = artifact.debug_info {
+            debug_info.populate(&mut program);
+        }
+        Ok(Input {
+            program,
+            entry_points: HashMap::new(),
+            struct_members: HashMap::new(),
+        })
+    } else {
+        Err(Error::UnknownFormat)
+    }
+}
+
+fn parse_program(src: &str) -> Result<Program, Error> {
+    ProgramParser::new()
+        .parse(src)
+        .map_err(|e| Error::Parse(e.map_token(|t| t.to_string()).to_string()))
+}
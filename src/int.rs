@@ -0,0 +1,294 @@
+use cairo_lang_sierra::{
+    extensions::{
+        int::{
+            signed::{SintConcrete, SintTraits},
+            signed128::Sint128Concrete,
+            unsigned::{UintConcrete, UintTraits},
+            unsigned128::Uint128Concrete,
+            unsigned256::Uint256Concrete,
+            unsigned512::Uint512Concrete,
+            IntMulTraits, IntOperator,
+        },
+        is_zero::IsZeroTraits,
+    },
+    program::{Invocation, StatementIdx},
+};
+
+use crate::{
+    ast::{BinaryOp, Block, Expr, Pattern, Stmt, Var},
+    build_branch, build_is_zero, declare,
+    emitter::LibfuncEmitter,
+    get_libfunc_name, get_type_name, hoist_join_variables, Error, FunctionContext, FunctionState,
+};
+
+/// Emits a libfunc of any of the unsigned integers up to 64 bits.
+pub fn build_uint<T: UintTraits + IntMulTraits + IsZeroTraits>(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+    invocation: &Invocation,
+    selector: &UintConcrete<T>,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let emitter = LibfuncEmitter::new(ctx, statement_idx, invocation);
+
+    match selector {
+        UintConcrete::Const(info) => emitter.build_const(body, state, info.c.into()),
+        UintConcrete::Operation(info) => emitter.build_operation(body, state, info.operator),
+        UintConcrete::SquareRoot(_) => {
+            emitter.build_builtin_expr(body, state, emitter.call("sqrt", 1)?)
+        }
+        UintConcrete::Equal(_) => emitter.build_equal(body, state),
+        UintConcrete::ToFelt252(_) => emitter.build_expr(body, state, into(&emitter)),
+        UintConcrete::FromFelt252(_) => emitter.build_try_from_felt252(body, state),
+        UintConcrete::IsZero(_) => build_is_zero(body, ctx, statement_idx, invocation, state),
+        UintConcrete::Divmod(_) => emitter.build_builtin_expr(body, state, divmod(&emitter)),
+        UintConcrete::WideMul(_) => emitter.build_call(body, state),
+        UintConcrete::Bitwise(_) => emitter.build_builtin_expr(body, state, bitwise(&emitter)),
+    }
+}
+
+/// Emits a libfunc of `u128`, which has some libfuncs of its own instead of a wide
+/// multiplication.
+pub fn build_uint128(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+    invocation: &Invocation,
+    selector: &Uint128Concrete,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let emitter = LibfuncEmitter::new(ctx, statement_idx, invocation);
+
+    match selector {
+        Uint128Concrete::Const(info) => emitter.build_const(body, state, info.c.into()),
+        Uint128Concrete::Operation(info) => emitter.build_operation(body, state, info.operator),
+        Uint128Concrete::SquareRoot(_) => {
+            emitter.build_builtin_expr(body, state, emitter.call("sqrt", 1)?)
+        }
+        Uint128Concrete::Equal(_) => emitter.build_equal(body, state),
+        Uint128Concrete::ToFelt252(_) => emitter.build_expr(body, state, into(&emitter)),
+        Uint128Concrete::FromFelt252(_) => emitter.build_builtin_match(
+            body,
+            state,
+            emitter.call("u128s_from_felt252", 1)?,
+            &[
+                "U128sFromFelt252Result::Narrow({})",
+                "U128sFromFelt252Result::Wide({})",
+            ],
+        ),
+        Uint128Concrete::IsZero(_) => build_is_zero(body, ctx, statement_idx, invocation, state),
+        Uint128Concrete::Divmod(_) => emitter.build_builtin_expr(body, state, divmod(&emitter)),
+        Uint128Concrete::Bitwise(_) => emitter.build_builtin_expr(body, state, bitwise(&emitter)),
+        Uint128Concrete::GuaranteeMul(_) => emitter.build_call(body, state),
+        // Only checks the guarantees, which are not shown.
+        Uint128Concrete::MulGuaranteeVerify(_) => emitter.rebind_builtins(body, state, 0),
+        Uint128Concrete::ByteReverse(_) => emitter.build_builtin_call(body, state),
+    }
+}
+
+/// Emits a libfunc of any of the signed integers up to 64 bits.
+pub fn build_sint<T: SintTraits + IntMulTraits + IsZeroTraits>(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+    invocation: &Invocation,
+    selector: &SintConcrete<T>,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let emitter = LibfuncEmitter::new(ctx, statement_idx, invocation);
+
+    match selector {
+        SintConcrete::Const(info) => emitter.build_const(body, state, info.c.into()),
+        SintConcrete::Equal(_) => emitter.build_equal(body, state),
+        SintConcrete::ToFelt252(_) => emitter.build_expr(body, state, into(&emitter)),
+        SintConcrete::FromFelt252(_) => emitter.build_try_from_felt252(body, state),
+        SintConcrete::Operation(info) => emitter.build_signed_operation(body, state, info.operator),
+        SintConcrete::Diff(_) => emitter.build_diff(body, state),
+        SintConcrete::IsZero(_) => build_is_zero(body, ctx, statement_idx, invocation, state),
+        SintConcrete::WideMul(_) => emitter.build_call(body, state),
+    }
+}
+
+/// Emits a libfunc of `i128`, which has no wide multiplication.
+pub fn build_sint128(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+    invocation: &Invocation,
+    selector: &Sint128Concrete,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let emitter = LibfuncEmitter::new(ctx, statement_idx, invocation);
+
+    match selector {
+        Sint128Concrete::Const(info) => emitter.build_const(body, state, info.c.into()),
+        Sint128Concrete::Equal(_) => emitter.build_equal(body, state),
+        Sint128Concrete::ToFelt252(_) => emitter.build_expr(body, state, into(&emitter)),
+        Sint128Concrete::FromFelt252(_) => emitter.build_try_from_felt252(body, state),
+        Sint128Concrete::Operation(info) => {
+            emitter.build_signed_operation(body, state, info.operator)
+        }
+        Sint128Concrete::Diff(_) => emitter.build_diff(body, state),
+        Sint128Concrete::IsZero(_) => build_is_zero(body, ctx, statement_idx, invocation, state),
+    }
+}
+
+/// Emits a libfunc of `u256`, shown as an integer rather than its limbs.
+pub fn build_uint256(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+    invocation: &Invocation,
+    selector: &Uint256Concrete,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let emitter = LibfuncEmitter::new(ctx, statement_idx, invocation);
+
+    match selector {
+        Uint256Concrete::IsZero(_) => build_is_zero(body, ctx, statement_idx, invocation, state),
+        Uint256Concrete::Divmod(_) => emitter.build_builtin_expr(body, state, divmod(&emitter)),
+        Uint256Concrete::SquareRoot(_) => {
+            emitter.build_builtin_expr(body, state, emitter.call("sqrt", 1)?)
+        }
+        Uint256Concrete::InvModN(_) => emitter.build_builtin_match(
+            body,
+            state,
+            emitter.call("inv_mod", 1)?,
+            &["Some({})", "None"],
+        ),
+    }
+}
+
+/// Emits a libfunc of `u512`.
+pub fn build_uint512(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+    invocation: &Invocation,
+    selector: &Uint512Concrete,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let emitter = LibfuncEmitter::new(ctx, statement_idx, invocation);
+
+    match selector {
+        Uint512Concrete::DivModU256(_) => emitter.build_builtin_expr(body, state, divmod(&emitter)),
+    }
+}
+
+/// The quotient and remainder of the arguments after the builtin.
+fn divmod(emitter: &LibfuncEmitter) -> Expr {
+    Expr::Tuple(vec![
+        emitter.binary(BinaryOp::Div),
+        emitter.binary(BinaryOp::Rem),
+    ])
+}
+
+/// The bitwise and, xor and or of the arguments after the builtin.
+fn bitwise(emitter: &LibfuncEmitter) -> Expr {
+    Expr::Tuple(vec![
+        emitter.binary(BinaryOp::BitAnd),
+        emitter.binary(BinaryOp::BitXor),
+        emitter.binary(BinaryOp::BitOr),
+    ])
+}
+
+/// The conversion of the only argument.
+fn into(emitter: &LibfuncEmitter) -> Expr {
+    Expr::method(emitter.arg(0), "into", vec![])
+}
+
+impl LibfuncEmitter<'_> {
+    /// Emits an overflowing operation as the wrapped result and an overflow flag, followed
+    /// by a branch on the flag. The wrapped result is bound to the result of the overflow
+    /// branch, and the other branch binds its own result to it.
+    fn build_operation(
+        &self,
+        body: &mut Block,
+        state: &mut FunctionState,
+        operator: IntOperator,
+    ) -> Result<(), Error> {
+        let block = self.block();
+        let wrapped = &self.invocation.branches[1].results[1];
+        let result = &self.invocation.branches[0].results[1];
+        let out_ty = &self.result_tys(1)?[1];
+        let op = match operator {
+            IntOperator::OverflowingAdd => BinaryOp::Add,
+            IntOperator::OverflowingSub => BinaryOp::Sub,
+        };
+
+        hoist_join_variables(body, self.ctx, block, &[wrapped], state)?;
+
+        body.push(Stmt::Let {
+            mutable: false,
+            pattern: Pattern::Tuple(vec![
+                Pattern::Typed(wrapped.into(), get_type_name(self.ctx, out_ty)?),
+                Pattern::Typed(Var::Overflowed(wrapped.id), "bool".to_string()),
+            ]),
+            ty: None,
+            expr: self.binary(op),
+            comment: None,
+        });
+
+        let mut then = Block::new();
+        self.rebind_builtins(&mut then, state, 0)?;
+        if result != wrapped {
+            then.push(declare(
+                state,
+                self.ctx,
+                result,
+                &self.result_tys(0)?[1],
+                false,
+                wrapped.into(),
+            )?);
+        }
+        build_branch(&mut then, self.ctx, block, 0, state)?;
+
+        let mut otherwise = Block::new();
+        self.rebind_builtins(&mut otherwise, state, 1)?;
+        build_branch(&mut otherwise, self.ctx, block, 1, state)?;
+
+        body.push(Stmt::If {
+            cond: Expr::Not(Box::new(Var::Overflowed(wrapped.id).into())),
+            then,
+            otherwise,
+        });
+        Ok(())
+    }
+
+    /// Emits an overflowing signed operation as a match on whether the result is in range,
+    /// below it or above it, each arm binding the wrapped result.
+    fn build_signed_operation(
+        &self,
+        body: &mut Block,
+        state: &mut FunctionState,
+        operator: IntOperator,
+    ) -> Result<(), Error> {
+        let op = match operator {
+            IntOperator::OverflowingAdd => BinaryOp::Add,
+            IntOperator::OverflowingSub => BinaryOp::Sub,
+        };
+        self.build_builtin_match(
+            body,
+            state,
+            self.binary(op),
+            &[
+                "SignedIntegerResult::InRange({})",
+                "SignedIntegerResult::Underflow({})",
+                "SignedIntegerResult::Overflow({})",
+            ],
+        )
+    }
+
+    /// Emits the difference of two signed integers, which is `Ok` with the unsigned
+    /// difference if it is positive or `Err` with it wrapped around otherwise.
+    fn build_diff(&self, body: &mut Block, state: &mut FunctionState) -> Result<(), Error> {
+        let name = get_libfunc_name(self.ctx, &self.invocation.libfunc_id);
+        self.build_builtin_match(
+            body,
+            state,
+            self.call(&name, self.builtins)?,
+            &["Ok({})", "Err({})"],
+        )
+    }
+}
@@ -0,0 +1,1892 @@
+//! A decompiler of Sierra programs into a verbose Cairo like code.
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let input = valley::load(&std::fs::read_to_string("program.sierra")?)?;
+//! let program = valley::Decompiler::new(&input.program)?.decompile_program()?;
+//! print!("{program}");
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod ast;
+mod cfg;
+mod contract_class;
+mod emitter;
+mod error;
+mod inline;
+mod input;
+mod int;
+mod panic;
+mod printer;
+mod simplify;
+mod starknet;
+mod validate;
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+};
+
+use ast::{Arm, BinaryOp, Block, Expr, Pattern, Stmt, Var};
+use cairo_lang_sierra::{
+    extensions::{
+        array::ArrayConcreteLibfunc,
+        circuit::CircuitTypeConcrete,
+        const_type::ConstConcreteLibfunc,
+        core::{CoreConcreteLibfunc, CoreLibfunc, CoreType, CoreTypeConcrete},
+        enm::EnumConcreteLibfunc,
+        felt252::{Felt252BinaryOperationConcrete, Felt252BinaryOperator, Felt252Concrete},
+        gas::GasConcreteLibfunc,
+        mem::MemConcreteLibfunc,
+        starknet::{secp256::Secp256PointTypeConcrete, StarkNetTypeConcrete},
+        structure::StructConcreteLibfunc,
+        ConcreteLibfunc,
+    },
+    ids::{ConcreteLibfuncId, ConcreteTypeId, FunctionId, UserTypeId, VarId},
+    program::{Function, GenStatement, GenericArg, Invocation, Program, StatementIdx},
+    program_registry::ProgramRegistry,
+};
+use cfg::{BlockId, Cfg};
+use emitter::LibfuncEmitter;
+use inline::build_inlined_loop;
+use int::{build_sint, build_sint128, build_uint, build_uint128, build_uint256, build_uint512};
+use num_bigint::{BigInt, Sign};
+use panic::{panic_result_values, PanicSugar};
+use starknet::build_starknet;
+
+pub use error::Error;
+pub use input::{load, Input};
+
+/// Decompiles the functions of a program.
+pub struct Decompiler<'a> {
+    program: &'a Program,
+    reg: ProgramRegistry<CoreType, CoreLibfunc>,
+    entry_points: HashMap<FunctionId, String>,
+    struct_members: HashMap<String, Vec<String>>,
+    short_names: bool,
+    implicits: Implicits,
+    keep_dead_code: bool,
+    keep_temporaries: bool,
+    keep_loop_calls: bool,
+    dump_cfg: bool,
+}
+
+impl<'a> Decompiler<'a> {
+    pub fn new(program: &'a Program) -> Result<Self, Error> {
+        Ok(Self {
+            program,
+            reg: ProgramRegistry::new(program)?,
+            entry_points: HashMap::new(),
+            struct_members: HashMap::new(),
+            short_names: false,
+            implicits: Implicits::default(),
+            keep_dead_code: false,
+            keep_temporaries: false,
+            keep_loop_calls: false,
+            dump_cfg: false,
+        })
+    }
+
+    /// Comments to print above the entry points of a contract, see [`Input`].
+    pub fn with_entry_points(mut self, entry_points: HashMap<FunctionId, String>) -> Self {
+        self.entry_points = entry_points;
+        self
+    }
+
+    /// The names of the members of structs by their path, see [`Input`]. Members of other
+    /// structs are named by their position.
+    pub fn with_struct_members(mut self, struct_members: HashMap<String, Vec<String>>) -> Self {
+        self.struct_members = struct_members;
+        self
+    }
+
+    /// Print debug names without their module paths, e.g. `u32` instead of
+    /// `core::integer::u32`.
+    pub fn with_short_names(mut self, short_names: bool) -> Self {
+        self.short_names = short_names;
+        self
+    }
+
+    /// How to show the builtins passed implicitly in Cairo, see [`Implicits`].
+    pub fn with_implicits(mut self, implicits: Implicits) -> Self {
+        self.implicits = implicits;
+        self
+    }
+
+    /// Keep the bindings which are never read, the drops and the unreachable statements,
+    /// which are removed by default.
+    pub fn with_keep_dead_code(mut self, keep_dead_code: bool) -> Self {
+        self.keep_dead_code = keep_dead_code;
+        self
+    }
+
+    /// Keep a binding for every temporary, which are inlined at the place they are read
+    /// by default.
+    pub fn with_keep_temporaries(mut self, keep_temporaries: bool) -> Self {
+        self.keep_temporaries = keep_temporaries;
+        self
+    }
+
+    /// Keep the calls to the functions the compiler lowers loops into, which are inlined as
+    /// a `loop` at their call sites by default.
+    pub fn with_keep_loop_calls(mut self, keep_loop_calls: bool) -> Self {
+        self.keep_loop_calls = keep_loop_calls;
+        self
+    }
+
+    /// Print the control flow graph of each function as comments.
+    pub fn with_dump_cfg(mut self, dump_cfg: bool) -> Self {
+        self.dump_cfg = dump_cfg;
+        self
+    }
+
+    /// Decompiles every function of the program, in order.
+    pub fn decompile_program(&self) -> Result<DecompiledProgram, Error> {
+        let functions = self
+            .program
+            .funcs
+            .iter()
+            .map(|func| self.build_function(func))
+            .collect::<Result<_, _>>()?;
+
+        Ok(DecompiledProgram { functions })
+    }
+
+    pub fn decompile_function(&self, id: &FunctionId) -> Result<DecompiledFunction, Error> {
+        let func = self
+            .program
+            .funcs
+            .iter()
+            .find(|func| func.id == *id)
+            .ok_or_else(|| Error::MissingFunction(id.clone()))?;
+
+        self.build_function(func)
+    }
+
+    /// The context to decompile the function in, as inlined into `callers` if any.
+    fn context<'b>(
+        &'b self,
+        func: &'b Function,
+        callers: Vec<FunctionId>,
+    ) -> Result<FunctionContext<'b>, Error> {
+        let statements = &self.program.statements;
+        let cfg = Cfg::new(statements, func.entry_point);
+
+        Ok(FunctionContext {
+            decompiler: self,
+            reg: &self.reg,
+            statements,
+            function: func,
+            callers,
+            panics: PanicSugar::new(&self.reg, statements, &cfg)?,
+            cfg,
+            struct_members: &self.struct_members,
+            short_names: self.short_names,
+            hide_implicits: self.implicits != Implicits::Show,
+        })
+    }
+
+    fn build_function(&self, func: &Function) -> Result<DecompiledFunction, Error> {
+        let reg = &self.reg;
+        let ctx = self.context(func, Vec::new())?;
+
+        let mut params = Vec::new();
+        let mut implicits = Vec::new();
+        for param in &func.params {
+            let ty_name = get_type_name(&ctx, &param.ty)?;
+            if !ctx.is_implicit(&param.ty)? {
+                params.push((ast::Var::from(&param.id), ty_name));
+            } else if self.implicits == Implicits::List {
+                implicits.push(ty_name);
+            }
+        }
+
+        let mut ret_types = Vec::new();
+        for param in func.signature.ret_types.iter() {
+            if ctx.is_implicit(param)? {
+                continue;
+            }
+            ret_types.push(match panic_result_values(reg, param)? {
+                // Functions which may panic return their values without the wrapping.
+                Some(values) if Some(param) == func.signature.ret_types.last() => {
+                    let names = values
+                        .iter()
+                        .map(|ty| get_type_name(&ctx, ty))
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    match &names[..] {
+                        [name] => name.clone(),
+                        names => format!("({})", names.join(", ")),
+                    }
+                }
+                _ => get_type_name(&ctx, param)?,
+            });
+        }
+
+        let mut body = Block::new();
+        if self.dump_cfg {
+            dump_cfg(&mut body, &ctx.cfg);
+        }
+
+        let mut state = FunctionState::default();
+
+        let tail_calls = self_tail_calls(&ctx)?;
+        if !tail_calls.is_empty() {
+            let (mutable, loop_body) = build_loop_function(&ctx, &tail_calls, &mut state)?;
+            for param in &func.params {
+                if mutable.contains(&param.id) {
+                    body.push(Stmt::Let {
+                        mutable: true,
+                        pattern: (&param.id).into(),
+                        ty: Some(get_type_name(&ctx, &param.ty)?),
+                        expr: (&param.id).into(),
+                        comment: None,
+                    });
+                }
+            }
+            body.push(Stmt::Loop(loop_body));
+        } else {
+            build_block(&mut body, &ctx, ctx.cfg.entry(), &mut state)?;
+        }
+
+        let mut function = ast::Function {
+            comment: self.entry_points.get(&func.id).cloned(),
+            name: get_function_name(&ctx, &func.id),
+            params,
+            ret_types,
+            implicits,
+            body,
+        };
+        simplify::propagate_copies(&mut function);
+        if !self.keep_dead_code {
+            simplify::remove_dead_code(&mut function);
+        }
+        if !self.keep_temporaries {
+            simplify::inline_temporaries(&mut function);
+        }
+        let undefined = validate::annotate_undefined(&mut function);
+
+        Ok(DecompiledFunction {
+            id: func.id.clone(),
+            name: function.name.clone(),
+            code: function.to_string(),
+            ast: function,
+            unsupported: state.unsupported,
+            undefined,
+        })
+    }
+}
+
+/// How the builtins which Cairo passes implicitly, like the range check, the gas builtin
+/// or the system, are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Implicits {
+    /// As any other variable, in the signatures, calls and returns.
+    #[default]
+    Show,
+    /// Left out of the code.
+    Hide,
+    /// Left out of the code, each function listing the ones it takes as `implicits(...)`.
+    List,
+}
+
+/// A decompiled function.
+#[derive(Debug, Clone)]
+pub struct DecompiledFunction {
+    pub id: FunctionId,
+    /// The name of the function, its debug name if the program has one.
+    pub name: String,
+    /// The code of the function, including its signature.
+    pub code: String,
+    /// The tree the code is printed from.
+    pub ast: ast::Function,
+    /// The names of the libfuncs emitted as is, once per statement.
+    pub unsupported: Vec<String>,
+    /// The variables read where they may not be defined, once per statement, which are
+    /// marked in the code. Any of them is a mistake of the decompiler.
+    pub undefined: Vec<ast::Var>,
+}
+
+/// The decompiled functions of a program, displayed as their code one after another.
+#[derive(Debug, Clone)]
+pub struct DecompiledProgram {
+    pub functions: Vec<DecompiledFunction>,
+}
+
+impl DecompiledProgram {
+    /// How many times each libfunc was emitted as is.
+    pub fn unsupported(&self) -> BTreeMap<&str, usize> {
+        let mut unsupported = BTreeMap::new();
+        for name in self.functions.iter().flat_map(|x| &x.unsupported) {
+            *unsupported.entry(name.as_str()).or_default() += 1;
+        }
+        unsupported
+    }
+}
+
+impl fmt::Display for DecompiledProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for function in &self.functions {
+            f.write_str(&function.code)?;
+        }
+        Ok(())
+    }
+}
+
+/// The state shared while decompiling a single function.
+pub(crate) struct FunctionContext<'a> {
+    pub decompiler: &'a Decompiler<'a>,
+    pub reg: &'a ProgramRegistry<CoreType, CoreLibfunc>,
+    pub statements: &'a [GenStatement<StatementIdx>],
+    pub function: &'a Function,
+    /// The functions the function is being inlined into, innermost last.
+    pub callers: Vec<FunctionId>,
+    pub cfg: Cfg,
+    /// The statements emitted as `panic!`, plain returns and `?`.
+    pub panics: PanicSugar,
+    /// The names of the members of structs by their path.
+    pub struct_members: &'a HashMap<String, Vec<String>>,
+    /// Print debug names without their module paths.
+    pub short_names: bool,
+    /// Leave the variables holding builtins out of the code.
+    pub hide_implicits: bool,
+}
+
+impl FunctionContext<'_> {
+    /// Formats a debug name according to the naming options.
+    pub fn format_path(&self, name: &str) -> String {
+        if self.short_names {
+            shorten_paths(name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Whether values of the type are left out of the code, as a builtin passed implicitly.
+    pub fn is_implicit(&self, ty: &ConcreteTypeId) -> Result<bool, Error> {
+        Ok(self.hide_implicits && is_builtin(self.reg, ty)?)
+    }
+}
+
+/// Whether the type is a builtin, which Cairo code passes implicitly.
+pub(crate) fn is_builtin(
+    reg: &ProgramRegistry<CoreType, CoreLibfunc>,
+    ty: &ConcreteTypeId,
+) -> Result<bool, Error> {
+    Ok(matches!(
+        reg.get_type(ty)?,
+        CoreTypeConcrete::RangeCheck(_)
+            | CoreTypeConcrete::RangeCheck96(_)
+            | CoreTypeConcrete::GasBuiltin(_)
+            | CoreTypeConcrete::Bitwise(_)
+            | CoreTypeConcrete::Pedersen(_)
+            | CoreTypeConcrete::Poseidon(_)
+            | CoreTypeConcrete::EcOp(_)
+            | CoreTypeConcrete::SegmentArena(_)
+            | CoreTypeConcrete::StarkNet(StarkNetTypeConcrete::System(_))
+            | CoreTypeConcrete::Circuit(
+                CircuitTypeConcrete::AddMod(_) | CircuitTypeConcrete::MulMod(_)
+            )
+    ))
+}
+
+/// A structured construct enclosing the code being emitted.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Scope {
+    /// The branches of a statement, which continue at their join block.
+    Join(BlockId),
+    /// A loop, exited through a `break` to the block following it, if any.
+    Loop {
+        header: BlockId,
+        follow: Option<BlockId>,
+    },
+}
+
+/// The mutable state while decompiling a single function.
+#[derive(Debug, Default)]
+pub(crate) struct FunctionState {
+    /// The enclosing constructs of the code being emitted, innermost last.
+    pub scopes: Vec<Scope>,
+    /// Variables declared before a branch or loop because they are used after it, they
+    /// are assigned instead of bound.
+    pub hoisted: HashSet<VarId>,
+    /// The names of the libfuncs emitted as unsupported, once per statement.
+    pub unsupported: Vec<String>,
+    /// The ids given to the variables of the inlined functions, which are taken.
+    pub inlined: HashSet<u64>,
+}
+
+/// Emits every statement of the block, then follows its successor. Blocks ending in a
+/// branch have each successor emitted by the branching statement and continue at the
+/// join point of the branches, if any.
+pub(crate) fn build_block(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    block: BlockId,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    if ctx.cfg.loop_body(block).is_some()
+        && !matches!(state.scopes.last(), Some(Scope::Loop { header, .. }) if *header == block)
+    {
+        return build_loop(body, ctx, block, state);
+    }
+
+    for statement_idx in ctx.cfg.blocks[block].statements() {
+        if !build_statement(body, ctx, statement_idx, state)? {
+            return Ok(());
+        }
+    }
+
+    match ctx.cfg.blocks[block].succs[..] {
+        [] => {}
+        [succ] => build_edge(body, ctx, block, succ, state)?,
+        _ => {
+            if let Some(join) = ctx.cfg.join(block) {
+                build_edge(body, ctx, block, join, state)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Emits the natural loop headed by `header` as a `loop`, then the code following it.
+pub(crate) fn build_loop(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    header: BlockId,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let loop_blocks = ctx
+        .cfg
+        .loop_body(header)
+        .expect("block should be a loop header");
+    let follow = ctx.cfg.ipdom(header).filter(|x| !loop_blocks.contains(x));
+
+    // The variables live when entering the loop and redefined by its body hold
+    // the state carried between iterations, they get reassigned instead of shadowed.
+    let statements = loop_blocks
+        .iter()
+        .flat_map(|&x| ctx.cfg.blocks[x].statements());
+    let defined = defined_vars(ctx, statements)?;
+    for (var, ty) in defined.values() {
+        if ctx.cfg.live_in(header).contains(var)
+            && !ctx.is_implicit(ty)?
+            && state.hoisted.insert(var.clone())
+        {
+            body.push(Stmt::Let {
+                mutable: true,
+                pattern: var.into(),
+                ty: Some(get_type_name(ctx, ty)?),
+                expr: var.into(),
+                comment: None,
+            });
+        }
+    }
+
+    let mut loop_body = Block::new();
+    state.scopes.push(Scope::Loop { header, follow });
+    let result = build_block(&mut loop_body, ctx, header, state);
+    state.scopes.pop();
+    result?;
+
+    body.push(Stmt::Loop(loop_body));
+
+    if let Some(follow) = follow {
+        build_edge(body, ctx, header, follow, state)?;
+    }
+
+    Ok(())
+}
+
+/// Emits the target of the given branch of `block`, up to the join point of the branches.
+pub(crate) fn build_branch(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    block: BlockId,
+    branch: usize,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let join = ctx.cfg.join(block);
+    let succ = ctx.cfg.blocks[block].succs[branch];
+
+    state.scopes.extend(join.map(Scope::Join));
+    let result = build_edge(body, ctx, block, succ, state);
+    if join.is_some() {
+        state.scopes.pop();
+    }
+
+    result
+}
+
+/// Whether `var` holds a new value after the invocation, as opposed to a variable moved
+/// into itself by a libfunc like `store_temp`.
+pub(crate) fn is_new_definition(
+    reg: &ProgramRegistry<CoreType, CoreLibfunc>,
+    invocation: &Invocation,
+    var: &VarId,
+) -> Result<bool, Error> {
+    Ok(!invocation.args.contains(var)
+        || !matches!(
+            reg.get_libfunc(&invocation.libfunc_id)?,
+            CoreConcreteLibfunc::Mem(_) | CoreConcreteLibfunc::Dup(_)
+        ))
+}
+
+/// Returns the variables defined by the given statements, with their types.
+pub(crate) fn defined_vars(
+    ctx: &FunctionContext,
+    statements: impl Iterator<Item = StatementIdx>,
+) -> Result<BTreeMap<u64, (VarId, ConcreteTypeId)>, Error> {
+    let mut defined = BTreeMap::new();
+
+    for statement_idx in statements {
+        if let GenStatement::Invocation(invocation) = &ctx.statements[statement_idx.0] {
+            let signatures = ctx
+                .reg
+                .get_libfunc(&invocation.libfunc_id)?
+                .branch_signatures();
+
+            for (branch, signature) in invocation.branches.iter().zip(signatures) {
+                for (var, var_info) in branch.results.iter().zip(&signature.vars) {
+                    if is_new_definition(ctx.reg, invocation, var)? {
+                        defined.insert(var.id, (var.clone(), var_info.ty.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(defined)
+}
+
+/// Declares the variables defined by the branches of `block` which are still used after
+/// their join point, so that each branch assigns them instead of binding its own.
+/// Variables in `bound` are already bound by the branching statement itself.
+pub(crate) fn hoist_join_variables(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    block: BlockId,
+    bound: &[&VarId],
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let Some(join) = ctx.cfg.join(block) else {
+        return Ok(());
+    };
+
+    // The blocks between the branch and the join. The statements of `block` before the
+    // branch are already emitted, only the results of the branch itself are in the region.
+    let mut region = Vec::new();
+    let mut visited = HashSet::from([block, join]);
+    let mut worklist = vec![block];
+
+    while let Some(current) = worklist.pop() {
+        for &succ in &ctx.cfg.blocks[current].succs {
+            if !ctx.cfg.is_back_edge(current, succ) && visited.insert(succ) {
+                region.push(succ);
+                worklist.push(succ);
+            }
+        }
+    }
+
+    let statements = region
+        .into_iter()
+        .flat_map(|x| ctx.cfg.blocks[x].statements());
+    let branch = ctx.cfg.blocks[block].end;
+    let defined = defined_vars(ctx, std::iter::once(branch).chain(statements))?;
+
+    // Variables read anywhere from the join onwards.
+    let mut used = HashSet::new();
+    let mut after = vec![join];
+    let mut visited = HashSet::from([join]);
+
+    while let Some(current) = after.pop() {
+        for statement_idx in ctx.cfg.blocks[current].statements() {
+            match &ctx.statements[statement_idx.0] {
+                GenStatement::Invocation(invocation) => {
+                    used.extend(invocation.args.iter().cloned())
+                }
+                GenStatement::Return(vars) => used.extend(vars.iter().cloned()),
+            }
+        }
+
+        for &succ in &ctx.cfg.blocks[current].succs {
+            if visited.insert(succ) {
+                after.push(succ);
+            }
+        }
+    }
+
+    for (var, ty) in defined.values() {
+        if used.contains(var)
+            && !bound.contains(&var)
+            && !state.hoisted.contains(var)
+            && !ctx.is_implicit(ty)?
+        {
+            body.push(Stmt::Declare {
+                mutable: true,
+                var: var.into(),
+                ty: get_type_name(ctx, ty)?,
+            });
+            state.hoisted.insert(var.clone());
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the statement binding `expr` to `var`, which is a plain assignment if the
+/// variable was hoisted before a branch.
+pub(crate) fn declare(
+    state: &FunctionState,
+    ctx: &FunctionContext,
+    var: &VarId,
+    ty: &ConcreteTypeId,
+    mutable: bool,
+    expr: Expr,
+) -> Result<Stmt, Error> {
+    Ok(if state.hoisted.contains(var) {
+        Stmt::bind(true, var.into(), None, expr)
+    } else {
+        Stmt::Let {
+            mutable,
+            pattern: var.into(),
+            ty: Some(get_type_name(ctx, ty)?),
+            expr,
+            comment: None,
+        }
+    })
+}
+
+/// Returns the statement binding `expr` to all of `vars` as a tuple.
+pub(crate) fn declare_tuple(
+    body: &mut Block,
+    state: &FunctionState,
+    ctx: &FunctionContext,
+    vars: &[VarId],
+    tys: &[ConcreteTypeId],
+    expr: Expr,
+) -> Result<Stmt, Error> {
+    let ty_names = tys
+        .iter()
+        .map(|x| get_type_name(ctx, x))
+        .collect::<Result<Vec<_>, _>>()?;
+    let pattern = Pattern::Tuple(vars.iter().map(Pattern::from).collect());
+    let ty = format!("({})", ty_names.join(", "));
+
+    declare_pattern(body, state, ctx, vars, tys, pattern, Some(ty), expr)
+}
+
+/// Returns the statement binding `expr` to `pattern`, which binds all of `vars`. If some
+/// of them were hoisted the pattern is assigned, declaring the rest beforehand.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn declare_pattern(
+    body: &mut Block,
+    state: &FunctionState,
+    ctx: &FunctionContext,
+    vars: &[VarId],
+    tys: &[ConcreteTypeId],
+    pattern: Pattern,
+    ty: Option<String>,
+    expr: Expr,
+) -> Result<Stmt, Error> {
+    if vars.iter().any(|x| state.hoisted.contains(x)) {
+        for (var, ty) in vars.iter().zip(tys) {
+            if !state.hoisted.contains(var) {
+                body.push(Stmt::Declare {
+                    mutable: false,
+                    var: var.into(),
+                    ty: get_type_name(ctx, ty)?,
+                });
+            }
+        }
+
+        return Ok(Stmt::bind(true, pattern, None, expr));
+    }
+
+    Ok(Stmt::bind(false, pattern, ty, expr))
+}
+
+/// Emits `expr` bound to the given variables, as a tuple if there are several. Implicit
+/// builtins are left out.
+pub(crate) fn bind(
+    body: &mut Block,
+    state: &FunctionState,
+    ctx: &FunctionContext,
+    vars: &[VarId],
+    tys: &[ConcreteTypeId],
+    expr: Expr,
+) -> Result<(), Error> {
+    let mut values = (Vec::new(), Vec::new());
+    for (var, ty) in vars.iter().zip(tys) {
+        if !ctx.is_implicit(ty)? {
+            values.0.push(var.clone());
+            values.1.push(ty.clone());
+        }
+    }
+
+    let stmt = match &values.0[..] {
+        [] => Stmt::expr(expr),
+        [var] => declare(state, ctx, var, &values.1[0], false, expr)?,
+        vars => declare_tuple(body, state, ctx, vars, &values.1, expr)?,
+    };
+    body.push(stmt);
+
+    Ok(())
+}
+
+/// Emits a libfunc which isn't decompiled yet, see [`LibfuncEmitter::build_unsupported`].
+pub(crate) fn build_unsupported(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+    invocation: &Invocation,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    LibfuncEmitter::new(ctx, statement_idx, invocation)
+        .with_builtins(0)
+        .build_unsupported(body, state)
+}
+
+/// The value moved by a libfunc like `store_temp`, `store_local` or `dup`. It is the last
+/// argument, as `store_local` takes the uninitialized local first.
+pub(crate) fn copied_value(invocation: &Invocation) -> &VarId {
+    invocation
+        .args
+        .last()
+        .expect("copy libfuncs should take the value")
+}
+
+/// Emits a libfunc moving its argument into its results, like `store_temp` and `dup`, as
+/// copies of the argument. Results keeping the id of the argument need none.
+pub(crate) fn build_copy(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    invocation: &Invocation,
+    state: &FunctionState,
+) -> Result<(), Error> {
+    let value = copied_value(invocation);
+    let signature = &ctx
+        .reg
+        .get_libfunc(&invocation.libfunc_id)?
+        .branch_signatures()[0];
+
+    for (result, var_info) in invocation.branches[0].results.iter().zip(&signature.vars) {
+        if result != value && !ctx.is_implicit(&var_info.ty)? {
+            body.push(declare(
+                state,
+                ctx,
+                result,
+                &var_info.ty,
+                false,
+                value.into(),
+            )?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Emits the deconstruction of a struct as a `let` destructuring it, or of a snapshot of a
+/// struct into snapshots of its members.
+pub(crate) fn build_deconstruct(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    invocation: &Invocation,
+    state: &FunctionState,
+) -> Result<(), Error> {
+    let libfunc = ctx.reg.get_libfunc(&invocation.libfunc_id)?;
+    let results = &invocation.branches[0].results;
+    let tys: Vec<ConcreteTypeId> = libfunc.branch_signatures()[0]
+        .vars
+        .iter()
+        .map(|x| x.ty.clone())
+        .collect();
+
+    let mut struct_ty = &libfunc.param_signatures()[0].ty;
+    let snapshot = match ctx.reg.get_type(struct_ty)? {
+        CoreTypeConcrete::Snapshot(inner) => {
+            struct_ty = &inner.ty;
+            true
+        }
+        _ => false,
+    };
+
+    let members = results.iter().map(Pattern::from);
+    let mut pattern = match get_member_names(ctx, struct_ty)? {
+        Some(names) => Pattern::Struct(names.into_iter().zip(members).collect()),
+        None => Pattern::Tuple(members.collect()),
+    };
+    if snapshot {
+        pattern = Pattern::Snapshot(Box::new(pattern));
+    }
+
+    let expr = (&invocation.args[0]).into();
+    let stmt = declare_pattern(body, state, ctx, results, &tys, pattern, None, expr)?;
+    body.push(stmt);
+    Ok(())
+}
+
+/// Emits a `*_is_zero` branch, binding the `NonZero` value in the non zero branch.
+pub(crate) fn build_is_zero(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+    invocation: &Invocation,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let block = ctx
+        .cfg
+        .block_of(statement_idx)
+        .expect("statement should be reachable");
+    let value = &invocation.args[0];
+
+    hoist_join_variables(body, ctx, block, &[], state)?;
+    let mut then = Block::new();
+    build_branch(&mut then, ctx, block, 0, state)?;
+
+    // The value is known to be non zero in this branch.
+    let non_zero = &invocation.branches[1].results[0];
+    let non_zero_ty = &ctx
+        .reg
+        .get_libfunc(&invocation.libfunc_id)?
+        .branch_signatures()[1]
+        .vars[0]
+        .ty;
+    let mut otherwise = vec![declare(
+        state,
+        ctx,
+        non_zero,
+        non_zero_ty,
+        false,
+        value.into(),
+    )?];
+    build_branch(&mut otherwise, ctx, block, 1, state)?;
+
+    body.push(Stmt::If {
+        cond: Expr::binary(value.into(), BinaryOp::Eq, Expr::literal(0)),
+        then,
+        otherwise,
+    });
+
+    Ok(())
+}
+
+/// Continues decompiling through the edge `from -> to`, which may leave the current
+/// branch or loop.
+pub(crate) fn build_edge(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    from: BlockId,
+    to: BlockId,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    for scope in state.scopes.iter().rev() {
+        match *scope {
+            Scope::Join(join) if join == to => return Ok(()),
+            Scope::Loop { header, .. } if header == to => {
+                body.push(Stmt::Continue);
+                return Ok(());
+            }
+            Scope::Loop { follow, .. } if follow == Some(to) => {
+                body.push(Stmt::Break);
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
+    if ctx.cfg.is_back_edge(from, to) {
+        body.push(Stmt::Comment(format!(
+            "jump back to statement {}",
+            ctx.cfg.blocks[to].start.0
+        )));
+        return Ok(());
+    }
+
+    build_block(body, ctx, to, state)
+}
+
+/// Whether the call at `statement_idx` has its results returned right away, only moved
+/// around by `store_temp` like libfuncs.
+pub(crate) fn is_tail_call(
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+) -> Result<bool, Error> {
+    let GenStatement::Invocation(call) = &ctx.statements[statement_idx.0] else {
+        return Ok(false);
+    };
+    let [call_branch] = &call.branches[..] else {
+        return Ok(false);
+    };
+
+    // Maps the variables holding a call result to the result.
+    let mut aliases: HashMap<&VarId, &VarId> = call_branch.results.iter().map(|x| (x, x)).collect();
+    let mut current = statement_idx.next(&call_branch.target);
+
+    for _ in 0..ctx.statements.len() {
+        match &ctx.statements[current.0] {
+            GenStatement::Invocation(invocation) => {
+                let [branch] = &invocation.branches[..] else {
+                    return Ok(false);
+                };
+
+                match ctx.reg.get_libfunc(&invocation.libfunc_id)? {
+                    CoreConcreteLibfunc::Mem(
+                        MemConcreteLibfunc::StoreTemp(_)
+                        | MemConcreteLibfunc::StoreLocal(_)
+                        | MemConcreteLibfunc::Rename(_),
+                    ) => {
+                        let Some(&result) = aliases.get(copied_value(invocation)) else {
+                            return Ok(false);
+                        };
+                        aliases.insert(&branch.results[0], result);
+                    }
+                    CoreConcreteLibfunc::BranchAlign(_)
+                    | CoreConcreteLibfunc::UnconditionalJump(_) => {}
+                    _ => return Ok(false),
+                }
+
+                current = current.next(&branch.target);
+            }
+            GenStatement::Return(vars) => {
+                return Ok(vars.len() == call_branch.results.len()
+                    && vars
+                        .iter()
+                        .zip(&call_branch.results)
+                        .all(|(var, result)| aliases.get(var) == Some(&result)));
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+/// The calls of the function to itself as a tail call, which is how the compiler lowers
+/// loops into helper functions.
+pub(crate) fn self_tail_calls(ctx: &FunctionContext) -> Result<Vec<StatementIdx>, Error> {
+    let mut calls = Vec::new();
+
+    for block in &ctx.cfg.blocks {
+        for statement_idx in block.statements() {
+            if let GenStatement::Invocation(invocation) = &ctx.statements[statement_idx.0] {
+                if let CoreConcreteLibfunc::FunctionCall(info) =
+                    ctx.reg.get_libfunc(&invocation.libfunc_id)?
+                {
+                    if info.function.id == ctx.function.id && is_tail_call(ctx, statement_idx)? {
+                        calls.push(statement_idx);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(calls)
+}
+
+/// Emits a function calling itself as a tail call, which is how the compiler lowers loops,
+/// as the body of a `loop` where the tail calls reassign the parameters and start over.
+/// Returns the parameters to declare mutable before the loop along with its body.
+pub(crate) fn build_loop_function(
+    ctx: &FunctionContext,
+    tail_calls: &[StatementIdx],
+    state: &mut FunctionState,
+) -> Result<(Vec<VarId>, Block), Error> {
+    // The parameters redefined by the body or passed anew to the tail calls hold the
+    // state carried between iterations, they get reassigned instead of shadowed.
+    let statements = ctx.cfg.blocks.iter().flat_map(|x| x.statements());
+    let defined = defined_vars(ctx, statements)?;
+    let mut passed = HashSet::new();
+    for statement_idx in tail_calls {
+        if let GenStatement::Invocation(call) = &ctx.statements[statement_idx.0] {
+            for (param, arg) in ctx.function.params.iter().zip(&call.args) {
+                if param.id != *arg {
+                    passed.insert(&param.id);
+                }
+            }
+        }
+    }
+
+    let mut mutable = Vec::new();
+    for param in &ctx.function.params {
+        if (defined.contains_key(&param.id.id) || passed.contains(&param.id))
+            && !ctx.is_implicit(&param.ty)?
+            && state.hoisted.insert(param.id.clone())
+        {
+            mutable.push(param.id.clone());
+        }
+    }
+
+    let mut loop_body = Block::new();
+    build_block(&mut loop_body, ctx, ctx.cfg.entry(), state)?;
+
+    Ok((mutable, loop_body))
+}
+
+/// Emits a single statement, returns false if the statement ends the current path.
+pub(crate) fn build_statement(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+    state: &mut FunctionState,
+) -> Result<bool, Error> {
+    let reg = ctx.reg;
+    let st = &ctx.statements[statement_idx.0];
+    let block = ctx
+        .cfg
+        .block_of(statement_idx)
+        .expect("statement should be reachable");
+
+    if ctx.panics.folded.contains(&statement_idx) {
+        return Ok(true);
+    }
+
+    match st {
+        GenStatement::Invocation(gen_invocation) => {
+            let lb = reg.get_libfunc(&gen_invocation.libfunc_id)?;
+
+            match lb {
+                CoreConcreteLibfunc::ApTracking(_) => {}
+                CoreConcreteLibfunc::Array(selector) => match selector {
+                    ArrayConcreteLibfunc::New(info) => {
+                        let outvarid = &gen_invocation.branches[0].results[0];
+                        let out_ty = &info.branch_signatures()[0].vars[0].ty;
+
+                        body.push(declare(
+                            state,
+                            ctx,
+                            outvarid,
+                            out_ty,
+                            true,
+                            Expr::call("Array::new", vec![]),
+                        )?);
+                    }
+                    ArrayConcreteLibfunc::SpanFromTuple(_)
+                    | ArrayConcreteLibfunc::TupleFromSpan(_)
+                    | ArrayConcreteLibfunc::PopFront(_)
+                    | ArrayConcreteLibfunc::PopFrontConsume(_)
+                    | ArrayConcreteLibfunc::Get(_)
+                    | ArrayConcreteLibfunc::Slice(_)
+                    | ArrayConcreteLibfunc::Len(_)
+                    | ArrayConcreteLibfunc::SnapshotPopFront(_)
+                    | ArrayConcreteLibfunc::SnapshotPopBack(_)
+                    | ArrayConcreteLibfunc::SnapshotMultiPopFront(_)
+                    | ArrayConcreteLibfunc::SnapshotMultiPopBack(_) => {
+                        build_unsupported(body, ctx, statement_idx, gen_invocation, state)?
+                    }
+                    ArrayConcreteLibfunc::Append(info) => {
+                        let outvarid = &gen_invocation.branches[0].results[0];
+                        let out_ty = &info.branch_signatures()[0].vars[0].ty;
+                        let array = &gen_invocation.args[0];
+
+                        body.push(Stmt::expr(Expr::method(
+                            array.into(),
+                            "append",
+                            vec![(&gen_invocation.args[1]).into()],
+                        )));
+                        body.push(declare(state, ctx, outvarid, out_ty, true, array.into())?);
+                    }
+                },
+                CoreConcreteLibfunc::BranchAlign(_) => {}
+                CoreConcreteLibfunc::Drop(_) => {
+                    let args = &gen_invocation.args;
+                    body.push(Stmt::expr(Expr::call("drop", vec![(&args[0]).into()])));
+                }
+                CoreConcreteLibfunc::Felt252(selector) => match selector {
+                    Felt252Concrete::Const(info) => {
+                        let outvarid = &gen_invocation.branches[0].results[0];
+                        let out_ty = &info.branch_signatures()[0].vars[0].ty;
+                        let mut stmt = declare(
+                            state,
+                            ctx,
+                            outvarid,
+                            out_ty,
+                            false,
+                            Expr::Literal(format_felt(&info.c)),
+                        )?;
+                        if short_string(&info.c).is_some() {
+                            stmt = stmt.with_comment(info.c.to_string());
+                        }
+
+                        body.push(stmt);
+                    }
+                    Felt252Concrete::BinaryOperation(op) => {
+                        let outvarid = &gen_invocation.branches[0].results[0];
+                        let out_ty = &op.branch_signatures()[0].vars[0].ty;
+                        let lhs = (&gen_invocation.args[0]).into();
+                        let (operator, rhs) = match op {
+                            Felt252BinaryOperationConcrete::WithVar(info) => {
+                                (info.operator, (&gen_invocation.args[1]).into())
+                            }
+                            Felt252BinaryOperationConcrete::WithConst(info) => {
+                                (info.operator, Expr::literal(&info.c))
+                            }
+                        };
+                        let operator = match operator {
+                            Felt252BinaryOperator::Add => BinaryOp::Add,
+                            Felt252BinaryOperator::Sub => BinaryOp::Sub,
+                            Felt252BinaryOperator::Mul => BinaryOp::Mul,
+                            Felt252BinaryOperator::Div => BinaryOp::Div,
+                        };
+
+                        body.push(declare(
+                            state,
+                            ctx,
+                            outvarid,
+                            out_ty,
+                            false,
+                            Expr::binary(lhs, operator, rhs),
+                        )?);
+                    }
+                    Felt252Concrete::IsZero(_) => {
+                        build_is_zero(body, ctx, statement_idx, gen_invocation, state)?;
+                    }
+                },
+                CoreConcreteLibfunc::Const(selector) => match selector {
+                    ConstConcreteLibfunc::AsBox(_) => {
+                        build_unsupported(body, ctx, statement_idx, gen_invocation, state)?
+                    }
+                    ConstConcreteLibfunc::AsImmediate(info) => {
+                        let outvarid = &gen_invocation.branches[0].results[0];
+                        let out_ty = &info.branch_signatures()[0].vars[0].ty;
+                        let const_type = reg.get_type(&info.const_type)?;
+
+                        let mut comment = None;
+                        let out_val = if let CoreTypeConcrete::Const(info) = const_type {
+                            let mut data = String::new();
+
+                            for x in &info.inner_data {
+                                match x {
+                                    GenericArg::Value(big_int) => {
+                                        data.push_str(&big_int.to_str_radix(10))
+                                    }
+                                    // Constant structs and enums.
+                                    _ => {
+                                        build_unsupported(
+                                            body,
+                                            ctx,
+                                            statement_idx,
+                                            gen_invocation,
+                                            state,
+                                        )?;
+                                        return Ok(true);
+                                    }
+                                }
+                            }
+
+                            // Felts holding text are shown as short strings.
+                            if let [GenericArg::Value(value)] = &info.inner_data[..] {
+                                if let (CoreTypeConcrete::Felt252(_), Some(literal)) =
+                                    (reg.get_type(&info.inner_ty)?, short_string(value))
+                                {
+                                    comment = Some(value.to_string());
+                                    data = literal;
+                                }
+                            }
+
+                            data
+                        } else {
+                            unreachable!()
+                        };
+
+                        let mut stmt =
+                            declare(state, ctx, outvarid, out_ty, false, Expr::Literal(out_val))?;
+                        if let Some(comment) = comment {
+                            stmt = stmt.with_comment(comment);
+                        }
+                        body.push(stmt);
+                    }
+                },
+                CoreConcreteLibfunc::FunctionCall(info) => {
+                    if info.function.id == ctx.function.id && is_tail_call(ctx, statement_idx)? {
+                        // The function is emitted as a loop, reassign the parameters at
+                        // once, as an argument may read another one, and start over.
+                        let mut params = Vec::new();
+                        let mut args = Vec::new();
+                        for (param, arg) in ctx.function.params.iter().zip(&gen_invocation.args) {
+                            if param.id != *arg && !ctx.is_implicit(&param.ty)? {
+                                params.push(Pattern::from(&param.id));
+                                args.push(Expr::from(arg));
+                            }
+                        }
+
+                        match params.len() {
+                            0 => {}
+                            1 => {
+                                body.push(Stmt::bind(true, params.remove(0), None, args.remove(0)))
+                            }
+                            _ => body.push(Stmt::bind(
+                                true,
+                                Pattern::Tuple(params),
+                                None,
+                                Expr::Tuple(args),
+                            )),
+                        }
+                        body.push(Stmt::Continue);
+
+                        return Ok(false);
+                    }
+
+                    if build_inlined_loop(body, ctx, gen_invocation, &info.function.id, state)? {
+                        return Ok(true);
+                    }
+
+                    let results = &gen_invocation.branches[0].results;
+                    let mut args = Vec::new();
+                    for (arg, ty) in gen_invocation
+                        .args
+                        .iter()
+                        .zip(&info.function.signature.param_types)
+                    {
+                        if !ctx.is_implicit(ty)? {
+                            args.push(arg.into());
+                        }
+                    }
+                    let call = Expr::call(get_function_name(ctx, &info.function.id), args);
+
+                    bind(
+                        body,
+                        state,
+                        ctx,
+                        results,
+                        &info.function.signature.ret_types,
+                        call,
+                    )?;
+                }
+                CoreConcreteLibfunc::Gas(selector) => match selector {
+                    // Both withdraws take the range check and the gas builtin first.
+                    GasConcreteLibfunc::WithdrawGas(_) => {
+                        LibfuncEmitter::new(ctx, statement_idx, gen_invocation)
+                            .with_builtins(2)
+                            .build_builtin_match(
+                                body,
+                                state,
+                                Expr::call("withdraw_gas", vec![]),
+                                &["Some({})", "None"],
+                            )?
+                    }
+                    GasConcreteLibfunc::RedepositGas(_)
+                    | GasConcreteLibfunc::GetAvailableGas(_) => {
+                        build_unsupported(body, ctx, statement_idx, gen_invocation, state)?
+                    }
+                    GasConcreteLibfunc::BuiltinWithdrawGas(_) => {
+                        let emitter = LibfuncEmitter::new(ctx, statement_idx, gen_invocation)
+                            .with_builtins(2);
+                        let expr = Expr::call("withdraw_gas_all", vec![emitter.arg(2)]);
+                        emitter.build_builtin_match(body, state, expr, &["Some({})", "None"])?
+                    }
+                    GasConcreteLibfunc::GetBuiltinCosts(_) => {
+                        LibfuncEmitter::new(ctx, statement_idx, gen_invocation).build_expr(
+                            body,
+                            state,
+                            Expr::call("get_builtin_costs", vec![]),
+                        )?
+                    }
+                },
+                CoreConcreteLibfunc::Uint8(selector) => {
+                    build_uint(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Uint16(selector) => {
+                    build_uint(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Uint32(selector) => {
+                    build_uint(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Uint64(selector) => {
+                    build_uint(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Uint128(selector) => {
+                    build_uint128(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Uint256(selector) => {
+                    build_uint256(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Uint512(selector) => {
+                    build_uint512(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Sint8(selector) => {
+                    build_sint(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Sint16(selector) => {
+                    build_sint(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Sint32(selector) => {
+                    build_sint(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Sint64(selector) => {
+                    build_sint(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Sint128(selector) => {
+                    build_sint128(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                CoreConcreteLibfunc::Mem(selector) => match selector {
+                    MemConcreteLibfunc::StoreTemp(_)
+                    | MemConcreteLibfunc::StoreLocal(_)
+                    | MemConcreteLibfunc::Rename(_) => {
+                        build_copy(body, ctx, gen_invocation, state)?
+                    }
+                    MemConcreteLibfunc::FinalizeLocals(_) => {}
+                    MemConcreteLibfunc::AllocLocal(_) => {}
+                },
+                CoreConcreteLibfunc::Dup(_) => build_copy(body, ctx, gen_invocation, state)?,
+                CoreConcreteLibfunc::UnconditionalJump(_) => {}
+                CoreConcreteLibfunc::Enum(selector) => match selector {
+                    EnumConcreteLibfunc::Init(info) => {
+                        let outvarid = &gen_invocation.branches[0].results[0];
+                        let args = &gen_invocation.args;
+                        let out_ty = &info.branch_signatures()[0].vars[0].ty;
+                        let variant = get_variant_name(ctx, out_ty, info.index);
+
+                        body.push(declare(
+                            state,
+                            ctx,
+                            outvarid,
+                            out_ty,
+                            false,
+                            Expr::call(variant, args.iter().map(Expr::from).collect()),
+                        )?);
+                    }
+                    EnumConcreteLibfunc::FromBoundedInt(_) => {
+                        build_unsupported(body, ctx, statement_idx, gen_invocation, state)?
+                    }
+                    EnumConcreteLibfunc::Match(_)
+                        if ctx.panics.propagations.contains_key(&statement_idx) =>
+                    {
+                        let matched = &gen_invocation.args[0];
+                        let values = &ctx.panics.propagations[&statement_idx];
+                        let expr = Expr::Try(Box::new(matched.into()));
+
+                        match &values[..] {
+                            [] => body.push(Stmt::expr(expr)),
+                            [(var, ty)] => body.push(declare(state, ctx, var, ty, false, expr)?),
+                            values => {
+                                let (vars, tys): (Vec<_>, Vec<_>) = values.iter().cloned().unzip();
+                                let stmt = declare_tuple(body, state, ctx, &vars, &tys, expr)?;
+                                body.push(stmt);
+                            }
+                        }
+
+                        // The error branch only propagates the panic.
+                        build_edge(body, ctx, block, ctx.cfg.blocks[block].succs[0], state)?;
+                        return Ok(false);
+                    }
+                    EnumConcreteLibfunc::Match(info) | EnumConcreteLibfunc::SnapshotMatch(info) => {
+                        let matched = &gen_invocation.args[0];
+                        let mut enum_ty = &info.param_signatures()[0].ty;
+                        if let CoreTypeConcrete::Snapshot(snapshot) = reg.get_type(enum_ty)? {
+                            enum_ty = &snapshot.ty;
+                        }
+
+                        match &gen_invocation.branches[..] {
+                            [] => {
+                                body.push(Stmt::Match {
+                                    expr: matched.into(),
+                                    arms: vec![],
+                                });
+                                return Ok(false);
+                            }
+                            [branch] => {
+                                let result = &branch.results[0];
+                                let pattern = Pattern::Variant {
+                                    path: get_variant_name(ctx, enum_ty, 0),
+                                    value: Some(Box::new(result.into())),
+                                };
+
+                                body.push(Stmt::bind(
+                                    state.hoisted.contains(result),
+                                    pattern,
+                                    None,
+                                    matched.into(),
+                                ));
+                            }
+                            branches => {
+                                hoist_join_variables(body, ctx, block, &[], state)?;
+
+                                let mut arms = Vec::new();
+                                for (i, branch) in branches.iter().enumerate() {
+                                    let result = &branch.results[0];
+                                    let mut arm_body = Block::new();
+
+                                    let binding = if state.hoisted.contains(result) {
+                                        arm_body.push(Stmt::bind(
+                                            true,
+                                            result.into(),
+                                            None,
+                                            Var::Value(result.id).into(),
+                                        ));
+                                        Var::Value(result.id)
+                                    } else {
+                                        result.into()
+                                    };
+
+                                    build_branch(&mut arm_body, ctx, block, i, state)?;
+                                    arms.push(Arm {
+                                        pattern: Pattern::Variant {
+                                            path: get_variant_name(ctx, enum_ty, i),
+                                            value: Some(Box::new(binding.into())),
+                                        },
+                                        body: arm_body,
+                                    });
+                                }
+
+                                body.push(Stmt::Match {
+                                    expr: matched.into(),
+                                    arms,
+                                });
+                            }
+                        }
+                    }
+                },
+                CoreConcreteLibfunc::Struct(selector) => match selector {
+                    StructConcreteLibfunc::Construct(info) => {
+                        let outvarid = &gen_invocation.branches[0].results[0];
+                        let args = gen_invocation.args.iter().map(Expr::from);
+                        let out_ty = &info.branch_signatures()[0].vars[0].ty;
+
+                        let expr = match get_member_names(ctx, out_ty)? {
+                            Some(names) => Expr::Struct(names.into_iter().zip(args).collect()),
+                            None => Expr::Tuple(args.collect()),
+                        };
+                        body.push(declare(state, ctx, outvarid, out_ty, false, expr)?);
+                    }
+                    StructConcreteLibfunc::Deconstruct(_)
+                    | StructConcreteLibfunc::SnapshotDeconstruct(_) => {
+                        build_deconstruct(body, ctx, gen_invocation, state)?
+                    }
+                },
+                // Keeps the value and takes a snapshot of it.
+                CoreConcreteLibfunc::SnapshotTake(info) => {
+                    let value = &gen_invocation.args[0];
+                    let results = &gen_invocation.branches[0].results;
+                    let tys = &info.branch_signatures()[0].vars;
+
+                    if results[0] != *value {
+                        body.push(declare(
+                            state,
+                            ctx,
+                            &results[0],
+                            &tys[0].ty,
+                            false,
+                            value.into(),
+                        )?);
+                    }
+                    body.push(declare(
+                        state,
+                        ctx,
+                        &results[1],
+                        &tys[1].ty,
+                        false,
+                        Expr::Snapshot(Box::new(value.into())),
+                    )?);
+                }
+                CoreConcreteLibfunc::StarkNet(selector) => {
+                    build_starknet(body, ctx, statement_idx, gen_invocation, selector, state)?
+                }
+                // Not decompiled yet, shown as a plain call to the libfunc.
+                CoreConcreteLibfunc::Bool(_)
+                | CoreConcreteLibfunc::Box(_)
+                | CoreConcreteLibfunc::Cast(_)
+                | CoreConcreteLibfunc::Circuit(_)
+                | CoreConcreteLibfunc::Coupon(_)
+                | CoreConcreteLibfunc::CouponCall(_)
+                | CoreConcreteLibfunc::Ec(_)
+                | CoreConcreteLibfunc::Nullable(_)
+                | CoreConcreteLibfunc::UnwrapNonZero(_)
+                | CoreConcreteLibfunc::Felt252Dict(_)
+                | CoreConcreteLibfunc::Felt252DictEntry(_)
+                | CoreConcreteLibfunc::Pedersen(_)
+                | CoreConcreteLibfunc::Poseidon(_)
+                | CoreConcreteLibfunc::Debug(_)
+                | CoreConcreteLibfunc::Bytes31(_)
+                | CoreConcreteLibfunc::BoundedInt(_) => {
+                    build_unsupported(body, ctx, statement_idx, gen_invocation, state)?
+                }
+            }
+        }
+        GenStatement::Return(vec) => {
+            if let Some(values) = ctx.panics.panics.get(&statement_idx) {
+                let mut data: Vec<Expr> = values
+                    .iter()
+                    .map(|x| Expr::Literal(format_felt(x)))
+                    .collect();
+                // The numeric values of the data, as some of it is shown as short strings.
+                let comment = values.iter().any(|x| short_string(x).is_some()).then(|| {
+                    let values: Vec<String> = values.iter().map(|x| x.to_string()).collect();
+                    values.join(", ")
+                });
+                body.push(Stmt::Panic {
+                    data: match data.len() {
+                        1 => data.remove(0),
+                        _ => Expr::Tuple(data),
+                    },
+                    comment,
+                });
+                return Ok(false);
+            }
+
+            let mut values = Vec::new();
+            for (i, var) in vec.iter().enumerate() {
+                match ctx.function.signature.ret_types.get(i) {
+                    Some(ty) if ctx.is_implicit(ty)? => {}
+                    _ => values.push(Expr::from(var)),
+                }
+            }
+            if let Some(wrapped) = ctx.panics.returns.get(&statement_idx) {
+                let mut wrapped: Vec<Expr> = wrapped.iter().map(Expr::from).collect();
+                values.pop();
+                values.push(match wrapped.len() {
+                    1 => wrapped.remove(0),
+                    _ => Expr::Tuple(wrapped),
+                });
+            } else if let Some(last) = ctx.function.signature.ret_types.last() {
+                // A result returned as is, propagate its panic.
+                if panic_result_values(reg, last)?.is_some() {
+                    if let Some(value) = values.pop() {
+                        values.push(Expr::Try(Box::new(value)));
+                    }
+                }
+            }
+
+            body.push(Stmt::Return(values));
+
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Formats a felt252 constant, as a short string literal if it is one.
+pub(crate) fn format_felt(value: &BigInt) -> String {
+    short_string(value).unwrap_or_else(|| value.to_string())
+}
+
+/// Decodes a felt252 whose big endian bytes are printable ASCII into a short string
+/// literal. Numbers can read as text too, like 20000 as `'N '`, so the callers keep the
+/// numeric value in a comment.
+pub(crate) fn short_string(value: &BigInt) -> Option<String> {
+    let (Sign::Plus, bytes) = value.to_bytes_be() else {
+        return None;
+    };
+    if !(1..=31).contains(&bytes.len()) || !bytes.iter().all(|x| (0x20..0x7f).contains(x)) {
+        return None;
+    }
+
+    let mut literal = String::from("'");
+    for &x in &bytes {
+        if x == b'\'' || x == b'\\' {
+            literal.push('\\');
+        }
+        literal.push(x as char);
+    }
+    literal.push('\'');
+
+    Some(literal)
+}
+
+/// Emits the blocks, edges, (post) dominators and joins of the graph as line comments.
+pub(crate) fn dump_cfg(body: &mut Block, cfg: &Cfg) {
+    let fmt_block = |block: Option<BlockId>| match block {
+        Some(block) => format!("b{block}"),
+        None => "-".to_string(),
+    };
+
+    for (id, block) in cfg.blocks.iter().enumerate() {
+        let succs: Vec<String> = block.succs.iter().map(|x| format!("b{x}")).collect();
+        let preds: Vec<String> = block.preds.iter().map(|x| format!("b{x}")).collect();
+
+        body.push(Stmt::Comment(format!(
+            "b{id}: statements {}..={} preds [{}] succs [{}] idom {} ipdom {} join {}",
+            block.start.0,
+            block.end.0,
+            preds.join(", "),
+            succs.join(", "),
+            fmt_block(cfg.idom(id)),
+            fmt_block(cfg.ipdom(id)),
+            fmt_block(cfg.join(id)),
+        )));
+    }
+}
+
+/// The name of a user function, its debug name if the program has one.
+pub(crate) fn get_function_name(ctx: &FunctionContext, id: &FunctionId) -> String {
+    match &id.debug_name {
+        Some(name) => ctx.format_path(name),
+        None => format!("func_{}", id.id),
+    }
+}
+
+/// The name of a libfunc, its debug name if the program has one.
+pub(crate) fn get_libfunc_name(ctx: &FunctionContext, id: &ConcreteLibfuncId) -> String {
+    match &id.debug_name {
+        Some(name) => ctx.format_path(name),
+        None => format!("libfunc_{}", id.id),
+    }
+}
+
+/// The name of an enum variant, qualified by its enum. Sierra doesn't keep variant names,
+/// so they are only known for the enums of the core library.
+pub(crate) fn get_variant_name(
+    ctx: &FunctionContext,
+    enum_ty: &ConcreteTypeId,
+    index: usize,
+) -> String {
+    let Some(debug_name) = &enum_ty.debug_name else {
+        return format!("Enum::Variant{index}");
+    };
+
+    // Drop the generic arguments, e.g. `core::option::Option::<u32>`.
+    let path = debug_name
+        .split('<')
+        .next()
+        .unwrap_or(debug_name)
+        .trim_end_matches("::");
+
+    let variant = match (path, index) {
+        ("core::option::Option", 0) => "Some".to_string(),
+        ("core::option::Option", 1) => "None".to_string(),
+        ("core::result::Result" | "core::panics::PanicResult", 0) => "Ok".to_string(),
+        ("core::result::Result" | "core::panics::PanicResult", 1) => "Err".to_string(),
+        ("core::bool", 0) => "False".to_string(),
+        ("core::bool", 1) => "True".to_string(),
+        _ => format!("Variant{index}"),
+    };
+
+    format!("{}::{variant}", ctx.format_path(path))
+}
+
+/// The names of the members of a struct, or `None` if it is a tuple. The names are known
+/// for some structs of the core library and the structs in the ABI of a contract, the
+/// members of the others are named `field_{i}`.
+pub(crate) fn get_member_names(
+    ctx: &FunctionContext,
+    struct_ty: &ConcreteTypeId,
+) -> Result<Option<Vec<String>>, Error> {
+    let CoreTypeConcrete::Struct(info) = ctx.reg.get_type(struct_ty)? else {
+        return Ok(Some(Vec::new()));
+    };
+    if let Some(GenericArg::UserType(user_type)) = info.info.long_id.generic_args.first() {
+        if *user_type == UserTypeId::from_string("Tuple") {
+            return Ok(None);
+        }
+    }
+
+    let known = struct_ty.debug_name.as_ref().and_then(|debug_name| {
+        // Drop the generic arguments, e.g. `core::array::Span::<u32>`.
+        let path = debug_name
+            .split('<')
+            .next()
+            .unwrap_or(debug_name)
+            .trim_end_matches("::");
+
+        let names: Vec<String> = match path {
+            "core::array::Span" => vec!["snapshot".to_string()],
+            "core::integer::u256" => vec!["low".to_string(), "high".to_string()],
+            "core::integer::u512" => (0..4).map(|i| format!("limb{i}")).collect(),
+            _ => ctx.struct_members.get(debug_name.as_str())?.clone(),
+        };
+        Some(names)
+    });
+
+    Ok(Some(match known {
+        Some(names) if names.len() == info.members.len() => names,
+        _ => (0..info.members.len())
+            .map(|i| format!("field_{i}"))
+            .collect(),
+    }))
+}
+
+/// Removes the module path of every item in a debug name, such that
+/// `core::panics::PanicResult::<(core::integer::u32,)>` becomes `PanicResult<(u32,)>`.
+pub(crate) fn shorten_paths(name: &str) -> String {
+    let mut buf = String::with_capacity(name.len());
+    let mut segment_start = None;
+    let mut chars = name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+
+            // Drop the module the path goes through, unless the `::` is a turbofish.
+            if chars.peek() != Some(&'<') {
+                if let Some(start) = segment_start {
+                    buf.truncate(start);
+                }
+            }
+            segment_start = None;
+            continue;
+        }
+
+        if c.is_alphanumeric() || c == '_' {
+            segment_start.get_or_insert(buf.len());
+        } else {
+            segment_start = None;
+        }
+
+        buf.push(c);
+    }
+
+    buf
+}
+
+/// The name of a type, its debug name if the program has one or else built from its
+/// structure.
+pub(crate) fn get_type_name(
+    ctx: &FunctionContext,
+    type_id: &ConcreteTypeId,
+) -> Result<String, Error> {
+    let ty = ctx.reg.get_type(type_id)?;
+
+    // The wide integers are structs of u128 limbs, shown as the integer they represent.
+    if let CoreTypeConcrete::Struct(info) = ty {
+        if let Some(GenericArg::UserType(user_type)) = info.info.long_id.generic_args.first() {
+            for name in ["u256", "u512"] {
+                if *user_type == UserTypeId::from_string(format!("core::integer::{name}")) {
+                    return Ok(name.to_string());
+                }
+            }
+        }
+    }
+
+    if let Some(name) = &type_id.debug_name {
+        let name = name
+            .replace("core::integer::u256", "u256")
+            .replace("core::integer::u512", "u512");
+        return Ok(ctx.format_path(&name));
+    }
+
+    Ok(match ty {
+        CoreTypeConcrete::Array(inner) => {
+            let inner_str = get_type_name(ctx, &inner.ty)?;
+            format!("Array<{}>", inner_str)
+        }
+        CoreTypeConcrete::Coupon(info) => {
+            format!("Coupon<{}>", get_function_name(ctx, &info.function_id))
+        }
+        CoreTypeConcrete::Bitwise(_) => "Bitwise".to_string(),
+        CoreTypeConcrete::Box(inner) => format!("Box<{}>", get_type_name(ctx, &inner.ty)?),
+        CoreTypeConcrete::Circuit(_) => format!("type_{}", type_id.id),
+        CoreTypeConcrete::Const(info) => {
+            let mut data = String::new();
+
+            for x in &info.inner_data {
+                match x {
+                    GenericArg::Value(big_int) => data.push_str(&big_int.to_str_radix(10)),
+                    // Constant structs and enums.
+                    _ => return Ok(format!("type_{}", type_id.id)),
+                }
+            }
+
+            data
+        }
+        CoreTypeConcrete::EcOp(_) => "EcOp".to_string(),
+        CoreTypeConcrete::EcPoint(_) => "EcPoint".to_string(),
+        CoreTypeConcrete::EcState(_) => "EcState".to_string(),
+        CoreTypeConcrete::Felt252(_) => "felt252".to_string(),
+        CoreTypeConcrete::GasBuiltin(_) => "GasBuiltin".to_string(),
+        CoreTypeConcrete::BuiltinCosts(_) => "BuiltinCosts".to_string(),
+        CoreTypeConcrete::Uint8(_) => "u8".to_string(),
+        CoreTypeConcrete::Uint16(_) => "u16".to_string(),
+        CoreTypeConcrete::Uint32(_) => "u32".to_string(),
+        CoreTypeConcrete::Uint64(_) => "u64".to_string(),
+        CoreTypeConcrete::Uint128(_) => "u128".to_string(),
+        CoreTypeConcrete::Uint128MulGuarantee(_) => "Uint128MulGuarantee".to_string(),
+        CoreTypeConcrete::Sint8(_) => "i8".to_string(),
+        CoreTypeConcrete::Sint16(_) => "i16".to_string(),
+        CoreTypeConcrete::Sint32(_) => "i32".to_string(),
+        CoreTypeConcrete::Sint64(_) => "i64".to_string(),
+        CoreTypeConcrete::Sint128(_) => "i128".to_string(),
+        CoreTypeConcrete::NonZero(inner) => {
+            let inner_str = get_type_name(ctx, &inner.ty)?;
+            format!("NonZero<{}>", inner_str)
+        }
+        CoreTypeConcrete::Nullable(inner) => {
+            let inner_str = get_type_name(ctx, &inner.ty)?;
+            format!("Nullable<{}>", inner_str)
+        }
+        CoreTypeConcrete::RangeCheck(_) => "RangeCheck".to_string(),
+        CoreTypeConcrete::RangeCheck96(_) => "RangeCheck96".to_string(),
+        CoreTypeConcrete::Uninitialized(inner) => {
+            let inner_str = get_type_name(ctx, &inner.ty)?;
+            format!("Uninitialized<{}>", inner_str)
+        }
+        CoreTypeConcrete::Enum(info) => {
+            let mut buf = String::new();
+
+            buf.push_str("Enum<");
+
+            let mut first = true;
+            for x in &info.variants {
+                if first {
+                    buf.push_str(&(get_type_name(ctx, x)?).to_string());
+                    first = false;
+                } else {
+                    buf.push_str(&format!(", {}", get_type_name(ctx, x)?));
+                }
+            }
+
+            buf.push('>');
+
+            buf
+        }
+        CoreTypeConcrete::Struct(info) => {
+            let mut buf = String::new();
+
+            buf.push('(');
+
+            let mut first = true;
+            for x in &info.members {
+                if first {
+                    buf.push_str(&(get_type_name(ctx, x)?).to_string());
+                    first = false;
+                } else {
+                    buf.push_str(&format!(", {}", get_type_name(ctx, x)?));
+                }
+            }
+
+            buf.push(')');
+
+            buf
+        }
+        CoreTypeConcrete::Felt252Dict(inner) => {
+            format!("Felt252Dict<{}>", get_type_name(ctx, &inner.ty)?)
+        }
+        CoreTypeConcrete::Felt252DictEntry(inner) => {
+            format!("Felt252DictEntry<{}>", get_type_name(ctx, &inner.ty)?)
+        }
+        CoreTypeConcrete::SquashedFelt252Dict(inner) => {
+            format!("SquashedFelt252Dict<{}>", get_type_name(ctx, &inner.ty)?)
+        }
+        CoreTypeConcrete::Pedersen(_) => "Pedersen".to_string(),
+        CoreTypeConcrete::Poseidon(_) => "Poseidon".to_string(),
+        CoreTypeConcrete::Span(inner) => format!("Span<{}>", get_type_name(ctx, &inner.ty)?),
+        CoreTypeConcrete::StarkNet(info) => match info {
+            StarkNetTypeConcrete::ClassHash(_) => "ClassHash".to_string(),
+            StarkNetTypeConcrete::ContractAddress(_) => "ContractAddress".to_string(),
+            StarkNetTypeConcrete::StorageBaseAddress(_) => "StorageBaseAddress".to_string(),
+            StarkNetTypeConcrete::StorageAddress(_) => "StorageAddress".to_string(),
+            StarkNetTypeConcrete::System(_) => "System".to_string(),
+            StarkNetTypeConcrete::Secp256Point(Secp256PointTypeConcrete::K1(_)) => {
+                "Secp256k1Point".to_string()
+            }
+            StarkNetTypeConcrete::Secp256Point(Secp256PointTypeConcrete::R1(_)) => {
+                "Secp256r1Point".to_string()
+            }
+            StarkNetTypeConcrete::Sha256StateHandle(_) => "Sha256StateHandle".to_string(),
+        },
+        CoreTypeConcrete::SegmentArena(_) => "SegmentArena".to_string(),
+        CoreTypeConcrete::Snapshot(inner) => {
+            let inner_str = get_type_name(ctx, &inner.ty)?;
+            format!("@{}", inner_str)
+        }
+        CoreTypeConcrete::Bytes31(_) => "bytes31".to_string(),
+        CoreTypeConcrete::BoundedInt(info) => {
+            format!(
+                "BoundedInt<{}, {}>",
+                info.range.lower,
+                &info.range.upper - 1
+            )
+        }
+    })
+}
@@ -0,0 +1,96 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use valley::DecompiledFunction;
+
+/// How the decompiled functions are split into files.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Split {
+    /// One file per module, holding all of its functions.
+    Module,
+    /// One file per function, in the directory of its module.
+    Function,
+}
+
+/// Writes each decompiled function under `dir`, the module path of the function being the
+/// path of its file. Functions without a debug name go to `lib.cairo_dec` or a file of
+/// their own.
+pub fn write_dir(
+    dir: &Path,
+    split: Split,
+    functions: &[DecompiledFunction],
+) -> Result<(), Box<dyn Error>> {
+    let mut files: BTreeMap<PathBuf, String> = BTreeMap::new();
+
+    for function in functions {
+        let mut segments = match &function.id.debug_name {
+            Some(name) => path_segments(name),
+            None => vec![format!("func_{}", function.id.id)],
+        };
+        if let Split::Module = split {
+            segments.pop();
+            if segments.is_empty() {
+                segments.push("lib".to_string());
+            }
+        }
+
+        let path: PathBuf = segments.iter().map(|x| sanitize(x)).collect();
+        files
+            .entry(dir.join(path).with_extension("cairo_dec"))
+            .or_default()
+            .push_str(&function.code);
+    }
+
+    for (path, code) in files {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, code)?;
+    }
+
+    Ok(())
+}
+
+/// Splits a path on its `::`, except the ones of the generic arguments and turbofishes.
+fn path_segments(name: &str) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    let mut depth = 0usize;
+    let mut chars = name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth = depth.saturating_sub(1),
+            ':' if depth == 0 && chars.peek() == Some(&':') => {
+                chars.next();
+                if chars.peek() != Some(&'<') {
+                    segments.push(String::new());
+                    continue;
+                }
+                segments.last_mut().unwrap().push(':');
+            }
+            _ => {}
+        }
+        segments.last_mut().unwrap().push(c);
+    }
+
+    segments
+}
+
+/// Replaces the characters which may not be valid in a file name.
+fn sanitize(segment: &str) -> String {
+    segment
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,328 @@
+use std::collections::{HashMap, HashSet};
+
+use cairo_lang_sierra::{
+    extensions::{
+        array::ArrayConcreteLibfunc,
+        const_type::ConstConcreteLibfunc,
+        core::{CoreConcreteLibfunc, CoreLibfunc, CoreType, CoreTypeConcrete},
+        enm::EnumConcreteLibfunc,
+        felt252::Felt252Concrete,
+        mem::MemConcreteLibfunc,
+        structure::StructConcreteLibfunc,
+        ConcreteLibfunc,
+    },
+    ids::{ConcreteTypeId, VarId},
+    program::{GenStatement, GenericArg, Invocation, StatementIdx},
+    program_registry::ProgramRegistry,
+};
+use num_bigint::BigInt;
+
+use crate::{cfg::Cfg, copied_value, Error};
+
+/// The statements of a function implementing panics through `PanicResult`, which are
+/// emitted as `panic!`, plain returns and `?` instead.
+#[derive(Debug, Default)]
+pub struct PanicSugar {
+    /// Statements folded into one of the sugared statements, they are not emitted.
+    pub folded: HashSet<StatementIdx>,
+    /// Returns of a `PanicResult::Ok`, with the values it wraps.
+    pub returns: HashMap<StatementIdx, Vec<VarId>>,
+    /// Returns of a `PanicResult::Err`, with the felts of the panic data.
+    pub panics: HashMap<StatementIdx, Vec<BigInt>>,
+    /// Matches on a `PanicResult` whose error is returned as is, with the variables bound
+    /// to the values wrapped by the `Ok` variant.
+    pub propagations: HashMap<StatementIdx, Vec<(VarId, ConcreteTypeId)>>,
+}
+
+impl PanicSugar {
+    pub fn new(
+        reg: &ProgramRegistry<CoreType, CoreLibfunc>,
+        statements: &[GenStatement<StatementIdx>],
+        cfg: &Cfg,
+    ) -> Result<Self, Error> {
+        let mut sugar = Self::default();
+
+        for block in &cfg.blocks {
+            let GenStatement::Return(vars) = &statements[block.end.0] else {
+                continue;
+            };
+            let Some(returned) = vars.last() else {
+                continue;
+            };
+
+            let finder = DefFinder {
+                reg,
+                statements,
+                start: block.start,
+            };
+            let mut folded = Vec::new();
+
+            let Some((init_idx, init)) = finder.find(block.end, returned, &mut folded)? else {
+                continue;
+            };
+            let CoreConcreteLibfunc::Enum(EnumConcreteLibfunc::Init(info)) =
+                reg.get_libfunc(&init.libfunc_id)?
+            else {
+                continue;
+            };
+            if panic_result_values(reg, &info.branch_signatures()[0].vars[0].ty)?.is_none() {
+                continue;
+            }
+            folded.push(init_idx);
+
+            let payload = &init.args[0];
+
+            if info.index == 0 {
+                let Some((tuple_idx, tuple)) = finder.find(init_idx, payload, &mut folded)? else {
+                    continue;
+                };
+                if !matches!(
+                    reg.get_libfunc(&tuple.libfunc_id)?,
+                    CoreConcreteLibfunc::Struct(StructConcreteLibfunc::Construct(_))
+                ) {
+                    continue;
+                }
+                folded.push(tuple_idx);
+
+                sugar.folded.extend(folded);
+                sugar.returns.insert(block.end, tuple.args.clone());
+            } else if let Some(data) = finder.find_panic_data(init_idx, payload, &mut folded)? {
+                sugar.folded.extend(folded);
+                sugar.panics.insert(block.end, data);
+            } else if let Some(propagation) =
+                find_propagation(reg, statements, cfg, block.start, payload)?
+            {
+                let (match_idx, values, deconstruct_idx) = propagation;
+                sugar.folded.extend(deconstruct_idx);
+                sugar.propagations.insert(match_idx, values);
+            }
+        }
+
+        Ok(sugar)
+    }
+}
+
+/// Returns the types of the values wrapped by the `Ok` variant if the type is a
+/// `PanicResult`, recognized by its `Err` variant holding a `(Panic, Array<felt252>)`.
+pub fn panic_result_values(
+    reg: &ProgramRegistry<CoreType, CoreLibfunc>,
+    ty: &ConcreteTypeId,
+) -> Result<Option<Vec<ConcreteTypeId>>, Error> {
+    let CoreTypeConcrete::Enum(info) = reg.get_type(ty)? else {
+        return Ok(None);
+    };
+    let [ok, err] = &info.variants[..] else {
+        return Ok(None);
+    };
+    let (CoreTypeConcrete::Struct(ok), CoreTypeConcrete::Struct(err)) =
+        (reg.get_type(ok)?, reg.get_type(err)?)
+    else {
+        return Ok(None);
+    };
+    let [panic, data] = &err.members[..] else {
+        return Ok(None);
+    };
+
+    let is_panic =
+        matches!(reg.get_type(panic)?, CoreTypeConcrete::Struct(x) if x.members.is_empty());
+    let is_data = matches!(
+        reg.get_type(data)?,
+        CoreTypeConcrete::Array(x) if matches!(reg.get_type(&x.ty)?, CoreTypeConcrete::Felt252(_))
+    );
+
+    Ok((is_panic && is_data).then(|| ok.members.clone()))
+}
+
+/// Finds the statements defining variables within a block, looking through the
+/// libfuncs which only move a variable around.
+struct DefFinder<'a> {
+    reg: &'a ProgramRegistry<CoreType, CoreLibfunc>,
+    statements: &'a [GenStatement<StatementIdx>],
+    start: StatementIdx,
+}
+
+impl<'a> DefFinder<'a> {
+    /// Returns the last statement before `before` which defines `var`, adding the moves
+    /// found on the way to `folded`.
+    fn find(
+        &self,
+        before: StatementIdx,
+        var: &VarId,
+        folded: &mut Vec<StatementIdx>,
+    ) -> Result<Option<(StatementIdx, &'a Invocation)>, Error> {
+        let mut var = var.clone();
+
+        for idx in (self.start.0..before.0).rev() {
+            let GenStatement::Invocation(invocation) = &self.statements[idx] else {
+                return Ok(None);
+            };
+            let [branch] = &invocation.branches[..] else {
+                return Ok(None);
+            };
+            if !branch.results.contains(&var) {
+                continue;
+            }
+
+            match self.reg.get_libfunc(&invocation.libfunc_id)? {
+                CoreConcreteLibfunc::Mem(
+                    MemConcreteLibfunc::StoreTemp(_)
+                    | MemConcreteLibfunc::StoreLocal(_)
+                    | MemConcreteLibfunc::Rename(_),
+                ) => {
+                    folded.push(StatementIdx(idx));
+                    var = copied_value(invocation).clone();
+                }
+                _ => return Ok(Some((StatementIdx(idx), invocation))),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the felts of the panic data if `payload` is a `(Panic, Array<felt252>)`
+    /// built from constants.
+    fn find_panic_data(
+        &self,
+        before: StatementIdx,
+        payload: &VarId,
+        folded: &mut Vec<StatementIdx>,
+    ) -> Result<Option<Vec<BigInt>>, Error> {
+        let Some((tuple_idx, tuple)) = self.find(before, payload, folded)? else {
+            return Ok(None);
+        };
+        if !matches!(
+            self.reg.get_libfunc(&tuple.libfunc_id)?,
+            CoreConcreteLibfunc::Struct(StructConcreteLibfunc::Construct(_))
+        ) {
+            return Ok(None);
+        }
+        folded.push(tuple_idx);
+
+        let Some((panic_idx, _)) = self.find(tuple_idx, &tuple.args[0], folded)? else {
+            return Ok(None);
+        };
+        folded.push(panic_idx);
+
+        // Walk the appends back to the creation of the array.
+        let mut data = Vec::new();
+        let (mut current_idx, mut array) = (tuple_idx, tuple.args[1].clone());
+
+        loop {
+            let Some((idx, invocation)) = self.find(current_idx, &array, folded)? else {
+                return Ok(None);
+            };
+            folded.push(idx);
+
+            match self.reg.get_libfunc(&invocation.libfunc_id)? {
+                CoreConcreteLibfunc::Array(ArrayConcreteLibfunc::New(_)) => break,
+                CoreConcreteLibfunc::Array(ArrayConcreteLibfunc::Append(_)) => {
+                    let Some((const_idx, const_invocation)) =
+                        self.find(idx, &invocation.args[1], folded)?
+                    else {
+                        return Ok(None);
+                    };
+                    let Some(value) = self.const_value(const_invocation)? else {
+                        return Ok(None);
+                    };
+                    folded.push(const_idx);
+                    data.push(value);
+
+                    current_idx = idx;
+                    array = invocation.args[0].clone();
+                }
+                _ => return Ok(None),
+            }
+        }
+
+        data.reverse();
+        Ok(Some(data))
+    }
+
+    fn const_value(&self, invocation: &Invocation) -> Result<Option<BigInt>, Error> {
+        Ok(match self.reg.get_libfunc(&invocation.libfunc_id)? {
+            CoreConcreteLibfunc::Felt252(Felt252Concrete::Const(info)) => Some(info.c.clone()),
+            CoreConcreteLibfunc::Const(ConstConcreteLibfunc::AsImmediate(info)) => {
+                match self.reg.get_type(&info.const_type)? {
+                    CoreTypeConcrete::Const(const_type) => match &const_type.inner_data[..] {
+                        [GenericArg::Value(value)] => Some(value.clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Checks if the block starting at `start` is the `Err` branch of a match on a
+/// `PanicResult`, and `payload` the error it matched. Returns the match, the variables
+/// holding the `Ok` values and the statement deconstructing them, if any.
+#[allow(clippy::type_complexity)]
+fn find_propagation(
+    reg: &ProgramRegistry<CoreType, CoreLibfunc>,
+    statements: &[GenStatement<StatementIdx>],
+    cfg: &Cfg,
+    start: StatementIdx,
+    payload: &VarId,
+) -> Result<
+    Option<(
+        StatementIdx,
+        Vec<(VarId, ConcreteTypeId)>,
+        Option<StatementIdx>,
+    )>,
+    Error,
+> {
+    let block = cfg.block_of(start).expect("block should be reachable");
+    let [pred] = cfg.blocks[block].preds[..] else {
+        return Ok(None);
+    };
+    let match_idx = cfg.blocks[pred].end;
+
+    let GenStatement::Invocation(invocation) = &statements[match_idx.0] else {
+        return Ok(None);
+    };
+    let CoreConcreteLibfunc::Enum(EnumConcreteLibfunc::Match(info)) =
+        reg.get_libfunc(&invocation.libfunc_id)?
+    else {
+        return Ok(None);
+    };
+    let [ok_branch, err_branch] = &invocation.branches[..] else {
+        return Ok(None);
+    };
+    if cfg.blocks[pred].succs[1] != block || err_branch.results[0] != *payload {
+        return Ok(None);
+    }
+    let Some(value_tys) = panic_result_values(reg, &info.param_signatures()[0].ty)? else {
+        return Ok(None);
+    };
+
+    // The `Ok` branch usually deconstructs the tuple of values right away.
+    let ok_var = &ok_branch.results[0];
+    let ok_block = &cfg.blocks[cfg.blocks[pred].succs[0]];
+
+    for idx in ok_block.statements() {
+        let GenStatement::Invocation(invocation) = &statements[idx.0] else {
+            break;
+        };
+
+        match reg.get_libfunc(&invocation.libfunc_id)? {
+            CoreConcreteLibfunc::BranchAlign(_) => {}
+            CoreConcreteLibfunc::Struct(StructConcreteLibfunc::Deconstruct(_))
+                if invocation.args[0] == *ok_var =>
+            {
+                let values = invocation.branches[0]
+                    .results
+                    .iter()
+                    .cloned()
+                    .zip(value_tys)
+                    .collect();
+                return Ok(Some((match_idx, values, Some(idx))));
+            }
+            _ => break,
+        }
+    }
+
+    let ok_ty = info.branch_signatures()[0].vars[0].ty.clone();
+    Ok(Some((match_idx, vec![(ok_var.clone(), ok_ty)], None)))
+}
@@ -0,0 +1,364 @@
+use std::fmt;
+
+use crate::ast::{Block, Expr, Function, Pattern, Stmt, Var};
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&print_function(self))
+    }
+}
+
+/// Prints the function as code, indenting by four spaces.
+pub(crate) fn print_function(function: &Function) -> String {
+    let mut buf = String::new();
+
+    if let Some(comment) = &function.comment {
+        buf.push_str(comment);
+    }
+
+    let params: Vec<String> = function
+        .params
+        .iter()
+        .map(|(var, ty)| format!("{}: {ty}", print_var(*var)))
+        .collect();
+    let implicits = match &function.implicits[..] {
+        [] => String::new(),
+        implicits => format!(" implicits({})", implicits.join(", ")),
+    };
+    buf.push_str(&format!(
+        "pub fn {}({}) -> ({}){implicits} {{\n",
+        function.name,
+        params.join(", "),
+        function.ret_types.join(", ")
+    ));
+
+    print_block(&mut buf, &function.body, 1);
+    buf.push_str("}\n\n");
+
+    buf
+}
+
+fn print_block(buf: &mut String, block: &Block, depth: usize) {
+    for (i, stmt) in block.iter().enumerate() {
+        // Returns are set apart from the code computing their values.
+        if i > 0 && matches!(stmt, Stmt::Return(_) | Stmt::Panic { .. }) {
+            buf.push('\n');
+        }
+        print_stmt(buf, stmt, depth);
+    }
+}
+
+fn print_stmt(buf: &mut String, stmt: &Stmt, depth: usize) {
+    let tabs = indent(depth);
+    let trailing = |comment: &Option<String>| match comment {
+        Some(comment) => format!(" // {comment}"),
+        None => String::new(),
+    };
+
+    match stmt {
+        Stmt::Declare { mutable, var, ty } => {
+            let keyword = if *mutable { "let mut" } else { "let" };
+            buf.push_str(&format!("{tabs}{keyword} {}: {ty};\n", print_var(*var)));
+        }
+        Stmt::Let {
+            mutable,
+            pattern,
+            ty,
+            expr,
+            comment,
+        } => {
+            let keyword = if *mutable { "let mut" } else { "let" };
+            let ty = match ty {
+                Some(ty) => format!(": {ty}"),
+                None => String::new(),
+            };
+            buf.push_str(&format!(
+                "{tabs}{keyword} {}{ty} = {};{}\n",
+                print_pattern(pattern),
+                print_expr(expr, depth),
+                trailing(comment)
+            ));
+        }
+        Stmt::Assign {
+            pattern,
+            expr,
+            comment,
+        } => buf.push_str(&format!(
+            "{tabs}{} = {};{}\n",
+            print_pattern(pattern),
+            print_expr(expr, depth),
+            trailing(comment)
+        )),
+        Stmt::Expr { expr, comment } => buf.push_str(&format!(
+            "{tabs}{};{}\n",
+            print_expr(expr, depth),
+            trailing(comment)
+        )),
+        Stmt::If {
+            cond,
+            then,
+            otherwise,
+        } => {
+            buf.push_str(&format!("{tabs}if {} {{\n", print_expr(cond, depth)));
+            print_else(buf, then, otherwise, depth);
+        }
+        Stmt::IfLet {
+            pattern,
+            expr,
+            then,
+            otherwise,
+        } => {
+            buf.push_str(&format!(
+                "{tabs}if let {} = {} {{\n",
+                print_pattern(pattern),
+                print_expr(expr, depth)
+            ));
+            print_else(buf, then, otherwise, depth);
+        }
+        Stmt::Match { expr, arms } if arms.is_empty() => {
+            buf.push_str(&format!("{tabs}match {} {{}}\n", print_expr(expr, depth)));
+        }
+        Stmt::Match { expr, arms } => {
+            buf.push_str(&format!("{tabs}match {} {{\n", print_expr(expr, depth)));
+            for arm in arms {
+                buf.push_str(&format!(
+                    "{tabs}    {} => {{\n",
+                    print_pattern(&arm.pattern)
+                ));
+                print_block(buf, &arm.body, depth + 2);
+                buf.push_str(&format!("{tabs}    }},\n"));
+            }
+            buf.push_str(&format!("{tabs}}}\n"));
+        }
+        Stmt::Loop(body) => {
+            buf.push_str(&format!("{tabs}loop {{\n"));
+            print_block(buf, body, depth + 1);
+            buf.push_str(&format!("{tabs}}}\n"));
+        }
+        Stmt::Continue => buf.push_str(&format!("{tabs}continue;\n")),
+        Stmt::Break => buf.push_str(&format!("{tabs}break;\n")),
+        Stmt::Return(values) => {
+            let values: Vec<String> = values.iter().map(|x| print_expr(x, depth)).collect();
+            buf.push_str(&format!("{tabs}return {};\n", values.join(", ")));
+        }
+        Stmt::Panic { data, comment } => {
+            buf.push_str(&format!(
+                "{tabs}panic!({});{}\n",
+                print_expr(data, depth),
+                trailing(comment)
+            ));
+        }
+        Stmt::Comment(text) => buf.push_str(&format!("{tabs}// {text}\n")),
+    }
+}
+
+/// Prints both branches of an `if`, whose condition is already printed. The `else` is
+/// left out when it is empty.
+fn print_else(buf: &mut String, then: &Block, otherwise: &Block, depth: usize) {
+    let tabs = indent(depth);
+
+    print_block(buf, then, depth + 1);
+    if !otherwise.is_empty() {
+        buf.push_str(&format!("{tabs}}} else {{\n"));
+        print_block(buf, otherwise, depth + 1);
+    }
+    buf.push_str(&format!("{tabs}}}\n"));
+}
+
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&print_var(*self))
+    }
+}
+
+fn print_var(var: Var) -> String {
+    match var {
+        Var::Sierra(id) => format!("v{id}"),
+        Var::Overflowed(id) => format!("v{id}_overflowed"),
+        Var::Value(id) => format!("value_{id}"),
+    }
+}
+
+fn print_pattern(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Var(var) => print_var(*var),
+        Pattern::Typed(var, ty) => format!("{}: {ty}", print_var(*var)),
+        Pattern::Tuple(patterns) => {
+            let patterns: Vec<String> = patterns.iter().map(print_pattern).collect();
+            print_tuple(&patterns)
+        }
+        // The members which aren't bound are left out.
+        Pattern::Struct(members) => {
+            let mut fields: Vec<String> = members
+                .iter()
+                .filter(|(_, x)| *x != Pattern::Wildcard)
+                .map(|(name, x)| format!("{name}: {}", print_pattern(x)))
+                .collect();
+            if fields.len() < members.len() {
+                fields.push("..".to_string());
+            }
+            match fields.is_empty() {
+                true => "Struct {}".to_string(),
+                false => format!("Struct {{ {} }}", fields.join(", ")),
+            }
+        }
+        Pattern::Snapshot(pattern) => format!("@{}", print_pattern(pattern)),
+        Pattern::Wildcard => "_".to_string(),
+        Pattern::Variant { path, value: None } => path.clone(),
+        Pattern::Variant {
+            path,
+            value: Some(value),
+        } => format!("{path}({})", print_pattern(value)),
+    }
+}
+
+/// Prints an expression of a statement at `depth`, which struct members are indented from.
+fn print_expr(expr: &Expr, depth: usize) -> String {
+    let list = |exprs: &[Expr]| {
+        exprs
+            .iter()
+            .map(|x| print_expr(x, depth))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match expr {
+        Expr::Var(var) => print_var(*var),
+        Expr::Literal(value) => value.clone(),
+        Expr::Call { name, args } => format!("{name}({})", list(args)),
+        Expr::MethodCall {
+            receiver,
+            method,
+            args,
+        } => format!(
+            "{}.{method}({})",
+            print_operand(receiver, depth),
+            list(args)
+        ),
+        Expr::Binary { op, lhs, rhs } => {
+            // Operands binding less tightly than the operator need parentheses, on the
+            // right even when they bind as tightly since operators associate to the left.
+            let operand = |operand: &Expr, min: u8| match operand {
+                Expr::Binary { op, .. } if op.precedence() < min => {
+                    format!("({})", print_expr(operand, depth))
+                }
+                _ => print_expr(operand, depth),
+            };
+            format!(
+                "{} {} {}",
+                operand(lhs, op.precedence()),
+                op.symbol(),
+                operand(rhs, op.precedence() + 1)
+            )
+        }
+        Expr::Not(value) => format!("!{}", print_operand(value, depth)),
+        Expr::Try(value) => format!("{}?", print_operand(value, depth)),
+        Expr::Snapshot(value) => format!("@{}", print_operand(value, depth)),
+        Expr::Tuple(values) => {
+            let values: Vec<String> = values.iter().map(|x| print_expr(x, depth)).collect();
+            print_tuple(&values)
+        }
+        Expr::Struct(members) if members.is_empty() => "Struct {}".to_string(),
+        Expr::Struct(members) => {
+            let tabs = indent(depth);
+            let mut buf = String::from("Struct {\n");
+            for (name, member) in members {
+                buf.push_str(&format!(
+                    "{tabs}    {name}: {},\n",
+                    print_expr(member, depth + 1)
+                ));
+            }
+            buf.push_str(&format!("{tabs}}}"));
+            buf
+        }
+    }
+}
+
+/// Prints the items of a tuple, with a trailing comma if there is a single one.
+fn print_tuple(items: &[String]) -> String {
+    match items {
+        [item] => format!("({item},)"),
+        items => format!("({})", items.join(", ")),
+    }
+}
+
+/// Prints the operand of a unary operator or method call, in parentheses if it is a binary
+/// operation.
+fn print_operand(expr: &Expr, depth: usize) -> String {
+    match expr {
+        Expr::Binary { .. } => format!("({})", print_expr(expr, depth)),
+        _ => print_expr(expr, depth),
+    }
+}
+
+fn indent(depth: usize) -> String {
+    " ".repeat(depth * 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(body: Block) -> Function {
+        Function {
+            comment: None,
+            name: "f".to_string(),
+            params: vec![(Var::Sierra(0), "felt252".to_string())],
+            ret_types: vec!["felt252".to_string()],
+            implicits: vec![],
+            body,
+        }
+    }
+
+    #[test]
+    fn returns_set_apart() {
+        let f = function(vec![
+            Stmt::If {
+                cond: Var::Sierra(0).into(),
+                then: vec![Stmt::Return(vec![Expr::literal(1)])],
+                otherwise: vec![Stmt::Panic {
+                    data: Expr::literal(2),
+                    comment: None,
+                }],
+            },
+            Stmt::expr(Expr::call("f", vec![])),
+            Stmt::Return(vec![Var::Sierra(0).into()]),
+        ]);
+
+        assert_eq!(
+            f.to_string(),
+            "pub fn f(v0: felt252) -> (felt252) {
+    if v0 {
+        return 1;
+    } else {
+        panic!(2);
+    }
+    f();
+
+    return v0;
+}
+
+"
+        );
+    }
+
+    #[test]
+    fn empty_else() {
+        let f = function(vec![Stmt::If {
+            cond: Var::Sierra(0).into(),
+            then: vec![Stmt::expr(Expr::call("f", vec![]))],
+            otherwise: vec![],
+        }]);
+
+        assert_eq!(
+            f.to_string(),
+            "pub fn f(v0: felt252) -> (felt252) {
+    if v0 {
+        f();
+    }
+}
+
+"
+        );
+    }
+}
@@ -0,0 +1,534 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::ast::{Arm, Block, Expr, Function, Pattern, Stmt, Var};
+
+/// Removes the variables which only copy another one, like the results of `store_temp`,
+/// `rename` and `dup` or a builtin rebound after a branch, reading the copied variable in
+/// their place. Assignments of a variable to itself are removed as well.
+///
+/// Variables which are assigned, like the state of a loop, are left alone as the copy
+/// must keep the value the variable had when the copy was made.
+pub(crate) fn propagate_copies(function: &mut Function) {
+    let mut assigned = HashSet::new();
+    collect_assigned(&function.body, &mut assigned);
+
+    propagate_block(&mut function.body, &assigned, &mut HashMap::new());
+}
+
+/// Collects the variables declared or assigned after being declared.
+fn collect_assigned(block: &Block, assigned: &mut HashSet<Var>) {
+    for stmt in block {
+        if let Stmt::Declare { .. } | Stmt::Assign { .. } = stmt {
+            assigned.extend(stmt.defined_vars());
+        }
+        for block in stmt.blocks() {
+            collect_assigned(block, assigned);
+        }
+    }
+}
+
+/// Removes the copies of the block, with `copies` mapping the copies in scope to the
+/// variable they read and their binding.
+fn propagate_block(
+    block: &mut Block,
+    assigned: &HashSet<Var>,
+    copies: &mut HashMap<Var, (Var, Stmt)>,
+) {
+    for mut stmt in std::mem::take(block) {
+        for expr in stmt.exprs_mut() {
+            expr.visit_vars_mut(&mut |var| {
+                if let Some((copied, _)) = copies.get(var) {
+                    *var = *copied;
+                }
+            });
+        }
+
+        match &stmt {
+            Stmt::Assign {
+                pattern: Pattern::Var(var),
+                expr: Expr::Var(copied),
+                comment: None,
+            } if var == copied => continue,
+            Stmt::Let {
+                pattern: Pattern::Var(var),
+                expr: Expr::Var(copied),
+                comment: None,
+                ..
+            } if var != copied && !assigned.contains(var) && !assigned.contains(copied) => {
+                let (var, copied) = (*var, *copied);
+                shadow(block, copies, var);
+                copies.insert(var, (copied, stmt));
+                continue;
+            }
+            _ => {}
+        }
+
+        for var in stmt.defined_vars() {
+            shadow(block, copies, var);
+        }
+        for nested in stmt.blocks_mut() {
+            propagate_block(nested, assigned, &mut copies.clone());
+        }
+        block.push(stmt);
+    }
+}
+
+/// Forgets about `var` being a copy, as it is being bound again. The copies of `var` get
+/// their binding back, as they hold its previous value.
+fn shadow(block: &mut Block, copies: &mut HashMap<Var, (Var, Stmt)>, var: Var) {
+    copies.remove(&var);
+
+    let mut stale: Vec<Var> = copies
+        .iter()
+        .filter(|(_, (copied, _))| *copied == var)
+        .map(|(copy, _)| *copy)
+        .collect();
+    stale.sort();
+
+    for copy in stale {
+        if let Some((_, binding)) = copies.remove(&copy) {
+            block.push(binding);
+        }
+    }
+}
+
+/// Inlines the variables read once which are bound to a side effect free expression, like a
+/// constant, an arithmetic operation, a struct or a snapshot, at the place they are read.
+///
+/// The expressions are never moved past a statement reading or binding the variables they
+/// read, so they see the same values, and values read by a snapshot aren't moved or dropped
+/// before it is taken. Bindings with a comment, like the value of a short string, are kept.
+pub(crate) fn inline_temporaries(function: &mut Function) {
+    let mut assigned = HashSet::new();
+    collect_assigned(&function.body, &mut assigned);
+
+    inline_block(
+        &mut function.body,
+        &assigned,
+        &mut HashMap::new(),
+        &mut HashSet::new(),
+    );
+}
+
+/// Inlines the temporaries of the block, with `pending` mapping the temporaries in scope to
+/// their expression and binding, and `placed` holding the ones already inlined or bound.
+fn inline_block(
+    block: &mut Block,
+    assigned: &HashSet<Var>,
+    pending: &mut HashMap<Var, (Expr, Stmt)>,
+    placed: &mut HashSet<Var>,
+) {
+    let mut rest: VecDeque<Stmt> = std::mem::take(block).into();
+
+    while let Some(mut stmt) = rest.pop_front() {
+        for var in reads(&stmt) {
+            bind_readers(block, pending, placed, var);
+        }
+        for expr in stmt.exprs_mut() {
+            substitute(expr, pending, placed);
+        }
+
+        if let Stmt::Let {
+            mutable: false,
+            pattern: Pattern::Var(var) | Pattern::Typed(var, _),
+            expr,
+            comment: None,
+            ..
+        } = &stmt
+        {
+            let var = *var;
+            if is_pure(expr)
+                && !assigned.contains(&var)
+                && expr.vars().iter().all(|x| !assigned.contains(x))
+                && count_reads(rest.iter(), var) == 1
+                && !rest.iter().any(|x| defines(x, var))
+            {
+                let expr = expr.clone();
+                forget(block, pending, placed, var);
+                pending.insert(var, (expr, stmt));
+                continue;
+            }
+        }
+
+        for var in stmt.defined_vars() {
+            forget(block, pending, placed, var);
+        }
+        for nested in stmt.blocks_mut() {
+            inline_block(nested, assigned, &mut pending.clone(), placed);
+        }
+        block.push(stmt);
+    }
+}
+
+/// Replaces the temporaries `expr` reads by their expression.
+fn substitute(
+    expr: &mut Expr,
+    pending: &mut HashMap<Var, (Expr, Stmt)>,
+    placed: &mut HashSet<Var>,
+) {
+    match expr {
+        Expr::Var(var) => {
+            if let Some((value, _)) = pending.remove(var) {
+                placed.insert(*var);
+                *expr = value;
+            }
+        }
+        Expr::Literal(_) => {}
+        Expr::Call { args, .. } | Expr::Tuple(args) => {
+            for arg in args {
+                substitute(arg, pending, placed);
+            }
+        }
+        Expr::Struct(members) => {
+            for (_, member) in members {
+                substitute(member, pending, placed);
+            }
+        }
+        Expr::MethodCall { receiver, args, .. } => {
+            substitute(receiver, pending, placed);
+            for arg in args {
+                substitute(arg, pending, placed);
+            }
+        }
+        Expr::Binary { lhs, rhs, .. } => {
+            substitute(lhs, pending, placed);
+            substitute(rhs, pending, placed);
+        }
+        Expr::Not(value) | Expr::Try(value) | Expr::Snapshot(value) => {
+            substitute(value, pending, placed)
+        }
+    }
+}
+
+/// Forgets about `var` being a temporary, as it is being bound again. The temporaries
+/// reading `var` get their binding back, as they read its previous value.
+fn forget(
+    block: &mut Block,
+    pending: &mut HashMap<Var, (Expr, Stmt)>,
+    placed: &mut HashSet<Var>,
+    var: Var,
+) {
+    pending.remove(&var);
+    bind_readers(block, pending, placed, var);
+}
+
+/// Gives their binding back to the temporaries reading `var`, which can't be moved past the
+/// statement about to be pushed.
+fn bind_readers(
+    block: &mut Block,
+    pending: &mut HashMap<Var, (Expr, Stmt)>,
+    placed: &mut HashSet<Var>,
+    var: Var,
+) {
+    let mut stale: Vec<Var> = pending
+        .iter()
+        .filter(|(_, (expr, _))| expr.vars().contains(&var))
+        .map(|(temporary, _)| *temporary)
+        .collect();
+    stale.sort();
+
+    for temporary in stale {
+        if let Some((_, binding)) = pending.remove(&temporary) {
+            if placed.insert(temporary) {
+                block.push(binding);
+            }
+        }
+    }
+}
+
+/// Whether evaluating the expression has no effect besides computing its value.
+fn is_pure(expr: &Expr) -> bool {
+    match expr {
+        Expr::Var(_) | Expr::Literal(_) => true,
+        Expr::Binary { lhs, rhs, .. } => is_pure(lhs) && is_pure(rhs),
+        Expr::Not(value) | Expr::Snapshot(value) => is_pure(value),
+        Expr::Tuple(values) => values.iter().all(is_pure),
+        Expr::Struct(members) => members.iter().all(|(_, x)| is_pure(x)),
+        Expr::Call { .. } | Expr::MethodCall { .. } | Expr::Try(_) => false,
+    }
+}
+
+/// How many times the statements and their blocks read `var`.
+fn count_reads<'a>(stmts: impl Iterator<Item = &'a Stmt>, var: Var) -> usize {
+    stmts
+        .map(|stmt| {
+            let reads = stmt.exprs().into_iter().flat_map(Expr::vars);
+            let nested = stmt
+                .blocks()
+                .into_iter()
+                .map(|x| count_reads(x.iter(), var));
+            reads.filter(|x| *x == var).count() + nested.sum::<usize>()
+        })
+        .sum()
+}
+
+/// The variables the statement and its blocks read.
+fn reads(stmt: &Stmt) -> Vec<Var> {
+    let nested = stmt.blocks().into_iter().flatten().flat_map(reads);
+    stmt.exprs()
+        .into_iter()
+        .flat_map(Expr::vars)
+        .chain(nested)
+        .collect()
+}
+
+/// Whether the statement or its blocks bind `var` again.
+fn defines(stmt: &Stmt, var: Var) -> bool {
+    stmt.defined_vars().contains(&var)
+        || stmt.blocks().into_iter().flatten().any(|x| defines(x, var))
+}
+
+/// Removes the code which doesn't contribute to the results of the function: the bindings
+/// which are never read, the drops, implicit in Cairo, and the statements which can't be
+/// reached as they follow a return, a panic or a jump. Unused bindings of calls keep the
+/// call, and unused variables of a pattern are replaced by wildcards.
+pub(crate) fn remove_dead_code(function: &mut Function) {
+    let mut assigned = HashSet::new();
+    collect_assigned(&function.body, &mut assigned);
+
+    // Removing an assignment may leave the variables it read unused elsewhere.
+    loop {
+        let read: HashSet<Var> = function.body.iter().flat_map(reads).collect();
+        if !remove_dead_block(&mut function.body, &assigned, &read) {
+            break;
+        }
+    }
+}
+
+/// Removes the dead code of the block, going backwards to know the variables read by the
+/// statements following each one. The assigned variables are kept as long as they are read
+/// anywhere in `read`. Returns whether anything was removed.
+fn remove_dead_block(block: &mut Block, assigned: &HashSet<Var>, read: &HashSet<Var>) -> bool {
+    let before = block.clone();
+
+    if let Some(end) = block.iter().position(diverges) {
+        block.truncate(end + 1);
+    }
+
+    let mut live = HashSet::new();
+    let mut kept = Vec::new();
+    for mut stmt in std::mem::take(block).into_iter().rev() {
+        for nested in stmt.blocks_mut() {
+            remove_dead_block(nested, assigned, read);
+        }
+
+        // The variables bound by the patterns of arms are read in their blocks.
+        let inner: HashSet<Var> = reads(&stmt).into_iter().collect();
+        let unused = |var: Var| match assigned.contains(&var) {
+            true => !read.contains(&var),
+            false => !live.contains(&var) && !inner.contains(&var),
+        };
+
+        let stmt = match stmt {
+            Stmt::Expr {
+                expr: Expr::Call { ref name, .. },
+                ..
+            } if name == "drop" => None,
+            Stmt::Declare { var, .. } if unused(var) => None,
+            Stmt::Let {
+                pattern,
+                expr,
+                comment,
+                ..
+            }
+            | Stmt::Assign {
+                pattern,
+                expr,
+                comment,
+            } if pattern.vars().into_iter().all(unused) => {
+                (!is_pure(&expr)).then_some(Stmt::Expr { expr, comment })
+            }
+            Stmt::Let {
+                mutable,
+                pattern,
+                ty,
+                expr,
+                comment,
+            } => Some(Stmt::Let {
+                mutable,
+                pattern: pattern.without(&unused),
+                ty,
+                expr,
+                comment,
+            }),
+            Stmt::IfLet {
+                pattern,
+                expr,
+                then,
+                otherwise,
+            } => Some(Stmt::IfLet {
+                pattern: pattern.without(&unused),
+                expr,
+                then,
+                otherwise,
+            }),
+            Stmt::Match { expr, arms } => Some(Stmt::Match {
+                expr,
+                arms: arms
+                    .into_iter()
+                    .map(|arm| Arm {
+                        pattern: arm.pattern.without(&unused),
+                        body: arm.body,
+                    })
+                    .collect(),
+            }),
+            stmt => Some(stmt),
+        };
+
+        if let Some(stmt) = stmt {
+            live.extend(reads(&stmt));
+            kept.push(stmt);
+        }
+    }
+
+    kept.reverse();
+    *block = kept;
+    *block != before
+}
+
+/// Whether the statements following the statement can't be reached.
+fn diverges(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return(_) | Stmt::Panic { .. } | Stmt::Continue | Stmt::Break => true,
+        Stmt::If { .. } | Stmt::IfLet { .. } | Stmt::Match { .. } => stmt
+            .blocks()
+            .into_iter()
+            .all(|block| block.iter().any(diverges)),
+        Stmt::Loop(body) => !breaks(body),
+        _ => false,
+    }
+}
+
+/// Whether the block breaks out of the loop it is in.
+fn breaks(block: &Block) -> bool {
+    block.iter().any(|stmt| match stmt {
+        Stmt::Break => true,
+        Stmt::Loop(_) => false,
+        stmt => stmt.blocks().into_iter().any(breaks),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::BinaryOp;
+
+    fn v(id: u64) -> Var {
+        Var::Sierra(id)
+    }
+
+    fn bind(id: u64, expr: Expr) -> Stmt {
+        Stmt::bind(false, Pattern::Var(v(id)), None, expr)
+    }
+
+    fn function(body: Block) -> Function {
+        Function {
+            comment: None,
+            name: "f".to_string(),
+            params: vec![],
+            ret_types: vec![],
+            implicits: vec![],
+            body,
+        }
+    }
+
+    #[test]
+    fn copy_read_in_branches() {
+        let mut f = function(vec![
+            bind(1, v(0).into()),
+            Stmt::If {
+                cond: v(2).into(),
+                then: vec![Stmt::Return(vec![v(1).into()])],
+                otherwise: vec![
+                    bind(
+                        3,
+                        Expr::binary(v(1).into(), BinaryOp::Add, Expr::literal(1)),
+                    ),
+                    Stmt::Return(vec![v(3).into()]),
+                ],
+            },
+        ]);
+        propagate_copies(&mut f);
+
+        assert_eq!(
+            f.body,
+            vec![Stmt::If {
+                cond: v(2).into(),
+                then: vec![Stmt::Return(vec![v(0).into()])],
+                otherwise: vec![
+                    bind(
+                        3,
+                        Expr::binary(v(0).into(), BinaryOp::Add, Expr::literal(1))
+                    ),
+                    Stmt::Return(vec![v(3).into()]),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn copy_of_shadowed_var() {
+        // The copy holds the previous value of `v0`, so it keeps its binding.
+        let body = vec![
+            bind(1, v(0).into()),
+            bind(0, Expr::call("f", vec![])),
+            Stmt::Return(vec![v(1).into(), v(0).into()]),
+        ];
+        let mut f = function(body.clone());
+        propagate_copies(&mut f);
+
+        assert_eq!(f.body, body);
+    }
+
+    #[test]
+    fn unused_call_keeps_its_effect() {
+        let mut f = function(vec![
+            bind(1, Expr::call("f", vec![v(0).into()])),
+            bind(
+                2,
+                Expr::binary(v(0).into(), BinaryOp::Add, Expr::literal(1)),
+            ),
+            Stmt::Return(vec![v(0).into()]),
+        ]);
+        remove_dead_code(&mut f);
+
+        assert_eq!(
+            f.body,
+            vec![
+                Stmt::expr(Expr::call("f", vec![v(0).into()])),
+                Stmt::Return(vec![v(0).into()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn unreachable_after_return() {
+        let mut f = function(vec![
+            Stmt::If {
+                cond: v(0).into(),
+                then: vec![
+                    Stmt::Return(vec![v(1).into()]),
+                    bind(2, Expr::call("f", vec![])),
+                ],
+                otherwise: vec![Stmt::Panic {
+                    data: Expr::literal(1),
+                    comment: None,
+                }],
+            },
+            Stmt::Return(vec![v(2).into()]),
+        ]);
+        remove_dead_code(&mut f);
+
+        assert_eq!(
+            f.body,
+            vec![Stmt::If {
+                cond: v(0).into(),
+                then: vec![Stmt::Return(vec![v(1).into()])],
+                otherwise: vec![Stmt::Panic {
+                    data: Expr::literal(1),
+                    comment: None,
+                }],
+            }]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use cairo_lang_sierra::{
+    extensions::starknet::{
+        secp256::{Secp256ConcreteLibfunc, Secp256OpConcreteLibfunc, Secp256Trait},
+        testing::TestingConcreteLibfunc,
+        StarkNetConcreteLibfunc,
+    },
+    program::{Invocation, StatementIdx},
+};
+
+use crate::{
+    ast::{Block, Expr},
+    emitter::LibfuncEmitter,
+    Error, FunctionContext, FunctionState,
+};
+
+/// Emits a StarkNet libfunc. The syscalls take the gas builtin and the system as their
+/// first arguments and are shown as a match on their `SyscallResult`.
+pub fn build_starknet(
+    body: &mut Block,
+    ctx: &FunctionContext,
+    statement_idx: StatementIdx,
+    invocation: &Invocation,
+    selector: &StarkNetConcreteLibfunc,
+    state: &mut FunctionState,
+) -> Result<(), Error> {
+    let emitter = LibfuncEmitter::new(ctx, statement_idx, invocation);
+
+    match selector {
+        StarkNetConcreteLibfunc::CallContract(_) => {
+            build_syscall(body, emitter, state, "call_contract_syscall")
+        }
+        StarkNetConcreteLibfunc::StorageRead(_) => {
+            build_syscall(body, emitter, state, "storage_read_syscall")
+        }
+        StarkNetConcreteLibfunc::StorageWrite(_) => {
+            build_syscall(body, emitter, state, "storage_write_syscall")
+        }
+        StarkNetConcreteLibfunc::EmitEvent(_) => {
+            build_syscall(body, emitter, state, "emit_event_syscall")
+        }
+        StarkNetConcreteLibfunc::GetBlockHash(_) => {
+            build_syscall(body, emitter, state, "get_block_hash_syscall")
+        }
+        StarkNetConcreteLibfunc::GetExecutionInfo(_) => {
+            build_syscall(body, emitter, state, "get_execution_info_syscall")
+        }
+        StarkNetConcreteLibfunc::GetExecutionInfoV2(_) => {
+            build_syscall(body, emitter, state, "get_execution_info_v2_syscall")
+        }
+        StarkNetConcreteLibfunc::Deploy(_) => build_syscall(body, emitter, state, "deploy_syscall"),
+        StarkNetConcreteLibfunc::Keccak(_) => build_syscall(body, emitter, state, "keccak_syscall"),
+        StarkNetConcreteLibfunc::Sha256ProcessBlock(_) => {
+            build_syscall(body, emitter, state, "sha256_process_block_syscall")
+        }
+        StarkNetConcreteLibfunc::LibraryCall(_) => {
+            build_syscall(body, emitter, state, "library_call_syscall")
+        }
+        StarkNetConcreteLibfunc::ReplaceClass(_) => {
+            build_syscall(body, emitter, state, "replace_class_syscall")
+        }
+        StarkNetConcreteLibfunc::SendMessageToL1(_) => {
+            build_syscall(body, emitter, state, "send_message_to_l1_syscall")
+        }
+        StarkNetConcreteLibfunc::Secp256(selector) => {
+            let syscall = match selector {
+                Secp256ConcreteLibfunc::K1(selector) => {
+                    format!("secp256k1_{}_syscall", secp256_op_name(selector))
+                }
+                Secp256ConcreteLibfunc::R1(selector) => {
+                    format!("secp256r1_{}_syscall", secp256_op_name(selector))
+                }
+            };
+            build_syscall(body, emitter, state, &syscall)
+        }
+        StarkNetConcreteLibfunc::ClassHashConst(info) => {
+            let expr = Expr::call(format!("class_hash_const::<{}>", info.c), vec![]);
+            emitter.build_expr(body, state, expr)
+        }
+        StarkNetConcreteLibfunc::ContractAddressConst(info) => {
+            let expr = Expr::call(format!("contract_address_const::<{}>", info.c), vec![]);
+            emitter.build_expr(body, state, expr)
+        }
+        StarkNetConcreteLibfunc::StorageBaseAddressConst(info) => {
+            let expr = Expr::call(format!("storage_base_address_const::<{}>", info.c), vec![]);
+            emitter.build_expr(body, state, expr)
+        }
+        StarkNetConcreteLibfunc::ClassHashTryFromFelt252(_)
+        | StarkNetConcreteLibfunc::ContractAddressTryFromFelt252(_)
+        | StarkNetConcreteLibfunc::StorageAddressTryFromFelt252(_) => {
+            emitter.build_try_from_felt252(body, state)
+        }
+        StarkNetConcreteLibfunc::ClassHashToFelt252(_)
+        | StarkNetConcreteLibfunc::ContractAddressToFelt252(_)
+        | StarkNetConcreteLibfunc::StorageAddressToFelt252(_) => {
+            emitter.build_expr(body, state, Expr::method(emitter.arg(0), "into", vec![]))
+        }
+        StarkNetConcreteLibfunc::StorageBaseAddressFromFelt252(_) => emitter.build_builtin_expr(
+            body,
+            state,
+            emitter.call("storage_base_address_from_felt252", 1)?,
+        ),
+        StarkNetConcreteLibfunc::StorageAddressFromBase(_) => {
+            emitter.build_expr(body, state, emitter.call("storage_address_from_base", 0)?)
+        }
+        StarkNetConcreteLibfunc::StorageAddressFromBaseAndOffset(_) => emitter.build_expr(
+            body,
+            state,
+            emitter.call("storage_address_from_base_and_offset", 0)?,
+        ),
+        StarkNetConcreteLibfunc::Sha256StateHandleInit(_) => {
+            emitter.build_expr(body, state, emitter.call("sha256_state_handle_init", 0)?)
+        }
+        StarkNetConcreteLibfunc::Sha256StateHandleDigest(_) => {
+            emitter.build_expr(body, state, emitter.call("sha256_state_handle_digest", 0)?)
+        }
+        StarkNetConcreteLibfunc::Testing(TestingConcreteLibfunc::Cheatcode(_)) => {
+            emitter.build_call(body, state)
+        }
+    }
+}
+
+fn secp256_op_name<T: Secp256Trait>(selector: &Secp256OpConcreteLibfunc<T>) -> &'static str {
+    match selector {
+        Secp256OpConcreteLibfunc::New(_) => "new",
+        Secp256OpConcreteLibfunc::Add(_) => "add",
+        Secp256OpConcreteLibfunc::Mul(_) => "mul",
+        Secp256OpConcreteLibfunc::GetPointFromX(_) => "get_point_from_x",
+        Secp256OpConcreteLibfunc::GetXy(_) => "get_xy",
+    }
+}
+
+/// Emits a syscall as a match on whether it succeeded, the error being the revert reason.
+fn build_syscall(
+    body: &mut Block,
+    emitter: LibfuncEmitter,
+    state: &mut FunctionState,
+    syscall: &str,
+) -> Result<(), Error> {
+    let emitter = emitter.with_builtins(2);
+    let call = emitter.call(syscall, emitter.builtins)?;
+
+    emitter.build_builtin_match(body, state, call, &["Ok({})", "Err({})"])
+}
@@ -0,0 +1,209 @@
+//! Checks of the decompiled code, catching the mistakes of the decompiler rather than of
+//! the program.
+
+use std::collections::HashSet;
+
+use crate::ast::{Block, Function, Stmt, Var};
+
+/// Finds the variables which may be read before being defined, on some path from the start
+/// of the function. A comment is added above each statement reading one, and the variables
+/// are returned once per statement.
+pub(crate) fn annotate_undefined(function: &mut Function) -> Vec<Var> {
+    let defined = function.params.iter().map(|(var, _)| *var).collect();
+    let mut undefined = Vec::new();
+
+    check_block(&mut function.body, defined, &mut undefined, &mut Vec::new());
+    undefined
+}
+
+/// Checks the block given the variables defined when entering it, pushing the variables
+/// defined when breaking out of the loop it is in to `breaks`. Returns the variables defined
+/// at its end, or `None` if it can't be reached.
+fn check_block(
+    block: &mut Block,
+    mut defined: HashSet<Var>,
+    undefined: &mut Vec<Var>,
+    breaks: &mut Vec<HashSet<Var>>,
+) -> Option<HashSet<Var>> {
+    let mut stmts = std::mem::take(block).into_iter();
+
+    while let Some(mut stmt) = stmts.next() {
+        let mut missing: Vec<Var> = stmt
+            .exprs()
+            .into_iter()
+            .flat_map(|x| x.vars())
+            .filter(|x| !defined.contains(x))
+            .collect();
+        missing.sort();
+        missing.dedup();
+
+        if !missing.is_empty() {
+            let names: Vec<String> = missing.iter().map(|x| x.to_string()).collect();
+            block.push(Stmt::Comment(format!(
+                "{} may be undefined here",
+                names.join(", ")
+            )));
+            undefined.extend(missing);
+        }
+
+        let end = match &mut stmt {
+            Stmt::Let { pattern, .. } | Stmt::Assign { pattern, .. } => {
+                defined.extend(pattern.vars());
+                Some(defined)
+            }
+            Stmt::If {
+                then, otherwise, ..
+            } => meet(
+                check_block(then, defined.clone(), undefined, breaks),
+                check_block(otherwise, defined, undefined, breaks),
+            ),
+            Stmt::IfLet {
+                pattern,
+                then,
+                otherwise,
+                ..
+            } => {
+                let mut bound = defined.clone();
+                bound.extend(pattern.vars());
+                meet(
+                    check_block(then, bound, undefined, breaks),
+                    check_block(otherwise, defined, undefined, breaks),
+                )
+            }
+            Stmt::Match { arms, .. } => arms.iter_mut().fold(None, |end, arm| {
+                let mut bound = defined.clone();
+                bound.extend(arm.pattern.vars());
+                meet(end, check_block(&mut arm.body, bound, undefined, breaks))
+            }),
+            Stmt::Loop(body) => {
+                let mut exits = Vec::new();
+                check_block(body, defined, undefined, &mut exits);
+                exits.into_iter().map(Some).reduce(meet).flatten()
+            }
+            Stmt::Break => {
+                breaks.push(defined);
+                None
+            }
+            Stmt::Continue | Stmt::Return(_) | Stmt::Panic { .. } => None,
+            // A declaration shadows any variable of the same name with an undefined one.
+            Stmt::Declare { var, .. } => {
+                defined.remove(var);
+                Some(defined)
+            }
+            Stmt::Expr { .. } | Stmt::Comment(_) => Some(defined),
+        };
+
+        block.push(stmt);
+        match end {
+            Some(end) => defined = end,
+            None => {
+                block.extend(stmts);
+                return None;
+            }
+        }
+    }
+
+    Some(defined)
+}
+
+/// The variables defined after either of two paths, `None` standing for a path which
+/// doesn't reach the end.
+fn meet(a: Option<HashSet<Var>>, b: Option<HashSet<Var>>) -> Option<HashSet<Var>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.intersection(&b).copied().collect()),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Expr, Pattern};
+
+    fn v(id: u64) -> Var {
+        Var::Sierra(id)
+    }
+
+    fn function(body: Block) -> Function {
+        Function {
+            comment: None,
+            name: "f".to_string(),
+            params: vec![(v(0), "felt252".to_string())],
+            ret_types: vec![],
+            implicits: vec![],
+            body,
+        }
+    }
+
+    fn comment(var: u64) -> Stmt {
+        Stmt::Comment(format!("v{var} may be undefined here"))
+    }
+
+    #[test]
+    fn undefined_read() {
+        let mut f = function(vec![Stmt::Return(vec![v(0).into(), v(1).into()])]);
+
+        assert_eq!(annotate_undefined(&mut f), [v(1)]);
+        assert_eq!(
+            f.body,
+            [comment(1), Stmt::Return(vec![v(0).into(), v(1).into()])]
+        );
+    }
+
+    #[test]
+    fn read_after_declaration() {
+        let mut f = function(vec![
+            Stmt::bind(false, Pattern::Var(v(1)), None, Expr::literal(1)),
+            Stmt::Declare {
+                mutable: true,
+                var: v(1),
+                ty: "felt252".to_string(),
+            },
+            Stmt::Return(vec![v(1).into()]),
+        ]);
+
+        assert_eq!(annotate_undefined(&mut f), [v(1)]);
+        assert_eq!(f.body[2], comment(1));
+    }
+
+    #[test]
+    fn defined_on_one_arm() {
+        let mut f = function(vec![
+            Stmt::Declare {
+                mutable: true,
+                var: v(1),
+                ty: "felt252".to_string(),
+            },
+            Stmt::If {
+                cond: v(0).into(),
+                then: vec![Stmt::bind(true, Pattern::Var(v(1)), None, Expr::literal(1))],
+                otherwise: vec![],
+            },
+            Stmt::Return(vec![v(1).into()]),
+        ]);
+
+        assert_eq!(annotate_undefined(&mut f), [v(1)]);
+        assert_eq!(f.body[2], comment(1));
+    }
+
+    #[test]
+    fn defined_on_every_arm() {
+        let assign = Stmt::bind(true, Pattern::Var(v(1)), None, Expr::literal(1));
+        let mut f = function(vec![
+            Stmt::Declare {
+                mutable: true,
+                var: v(1),
+                ty: "felt252".to_string(),
+            },
+            Stmt::If {
+                cond: v(0).into(),
+                then: vec![assign.clone()],
+                otherwise: vec![assign],
+            },
+            Stmt::Return(vec![v(1).into()]),
+        ]);
+
+        assert!(annotate_undefined(&mut f).is_empty());
+    }
+}
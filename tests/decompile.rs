@@ -0,0 +1,147 @@
+use valley::{Decompiler, Input};
+
+fn decompile(name: &str) -> String {
+    let path = format!("{}/tests/programs/{name}", env!("CARGO_MANIFEST_DIR"));
+    let Input {
+        program,
+        entry_points,
+        struct_members,
+    } = valley::load(&std::fs::read_to_string(path).unwrap()).unwrap();
+
+    Decompiler::new(&program)
+        .unwrap()
+        .with_entry_points(entry_points)
+        .with_struct_members(struct_members)
+        .decompile_program()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn shared_join_after_returning_arm() {
+    let code = decompile("shared_join.sierra");
+
+    // The addition both arms of the `if` continue at is emitted once after it, even
+    // though the subtraction in one of them returns on overflow.
+    assert_eq!(code.matches(" + 1;").count(), 1, "{code}");
+    assert!(code.contains("return v0, v8;"), "{code}");
+}
+
+#[test]
+fn tail_recursive_loop_reassigns_parameters() {
+    let code = decompile("fib_no_gas.sierra");
+    let n = "v12638194897137039473";
+
+    // The parameters carry the state between iterations instead of being shadowed.
+    assert!(
+        code.contains(&format!("let mut {n}: felt252 = {n};")),
+        "{code}"
+    );
+    assert!(code.contains(&format!("    {n} = {n} + -1;")), "{code}");
+    assert!(!code.contains(&format!("let {n}")), "{code}");
+    assert!(
+        code.contains(
+            "(v12638187200555641996, v12638190499090526629) = \
+         (v12638190499090526629, v11807312940536349088);"
+        ),
+        "{code}"
+    );
+}
+
+#[test]
+fn loop_helper_inlined_in_caller() {
+    let code = decompile("loop_call.sierra");
+    let main = &code[code.find("pub fn main").unwrap()..];
+
+    // The helper's returns break out of the loop with the result of the call, and its
+    // variables clashing with the caller's are renamed.
+    assert!(main.contains("// sum inlined as a loop"), "{code}");
+    assert!(
+        main.contains("let mut v3300820206774538237: felt252;"),
+        "{code}"
+    );
+    assert!(
+        main.contains("let mut v12638194897137039474: felt252 = v12638194897137039473;"),
+        "{code}"
+    );
+    assert!(
+        main.contains("v3300820206774538237 = v16655378055757661210;\n            break;"),
+        "{code}"
+    );
+    assert!(!main.contains("sum("), "{code}");
+}
+
+#[test]
+fn short_strings_show_their_value() {
+    let code = decompile("short_string.sierra");
+
+    // The bindings of short strings aren't inlined, which would drop the value comment.
+    assert_eq!(
+        code.matches("= 'hello'; // 448378203247").count(),
+        2,
+        "{code}"
+    );
+    // Numbers reading as text are decoded too, the comment keeps their value.
+    assert!(code.contains("= 'N '; // 20000"), "{code}");
+
+    let code = decompile("simple.sierra");
+    assert!(
+        code.contains("panic!('u32_add Overflow'); // 155785504323917466144735657540098748279"),
+        "{code}"
+    );
+}
+
+#[test]
+fn contract_class_round_trips() {
+    // Both compiled by `starknet-compile`, from the test data of `cairo-lang-starknet`.
+    let dir = format!("{}/tests/programs", env!("CARGO_MANIFEST_DIR"));
+    let class = std::fs::read_to_string(format!("{dir}/minimal_contract.contract_class.json"));
+    let sierra = std::fs::read_to_string(format!("{dir}/minimal_contract.sierra"));
+
+    let input = valley::load(&class.unwrap()).unwrap();
+    assert_eq!(input.program.to_string(), sierra.unwrap());
+
+    let code = decompile("minimal_contract.contract_class.json");
+    assert!(
+        code.contains(
+            "// external `empty`, selector: \
+             0x1fc3f77ebc090777f567969ad9823cf6334ab888acb385ca72668ec5adbde80"
+        ),
+        "{code}"
+    );
+}
+
+#[test]
+fn store_local_copies_the_stored_value() {
+    let code = decompile("store_local.sierra");
+
+    // The local allocated by `alloc_local` is only a slot, the value is what is read.
+    assert!(code.contains("return 7;"), "{code}");
+    assert!(!code.contains("v1"), "{code}");
+}
+
+#[test]
+fn tail_call_returned_through_local() {
+    let code = decompile("tail_call_local.sierra");
+
+    assert!(code.contains("continue;"), "{code}");
+    assert!(!code.contains("f(v0)"), "{code}");
+}
+
+#[test]
+fn panic_result_returned_through_local() {
+    let code = decompile("panic_result_local.sierra");
+
+    // The `PanicResult::Ok` is found through the local it is stored in.
+    assert!(code.contains("return 7;"), "{code}");
+}
+
+#[test]
+fn join_keeps_bindings_before_the_branch() {
+    let code = decompile("join_after_binding.sierra");
+
+    // `v2` is bound before the branch, it isn't declared again for the join.
+    assert!(code.contains("let v2: felt252 = v0 + 7;"), "{code}");
+    assert!(!code.contains("let mut v2"), "{code}");
+    assert!(code.contains("return v2;"), "{code}");
+}